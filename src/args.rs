@@ -1,7 +1,33 @@
 use std::env;
 
-pub fn get_arg() -> Option<String> {
+pub enum Command {
+    /// `beetle file.bt`
+    Run(String),
+    /// `beetle check [--types] file.bt` parses the program without running it,
+    /// `--types` also looks for type errors
+    Check { path: String, types: bool },
+    /// `beetle test file.bt` runs the tests in the file and the files it imports
+    Test(String),
+}
+
+pub fn get_command() -> Option<Command> {
     let mut args = env::args();
     args.next(); // beetle
-    args.next()
-}
\ No newline at end of file
+    let first = args.next()?;
+    if first == "test" {
+        let path = args.next()?;
+        return if args.next().is_none() {Some(Command::Test(path))} else {None};
+    }
+    if first != "check" {return Some(Command::Run(first));}
+
+    let mut types = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--types" => types = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => return None,
+        }
+    }
+    Some(Command::Check { path: path?, types })
+}
@@ -1,13 +1,49 @@
-use std::{collections::HashMap, process::exit};
+use std::{collections::{HashMap, HashSet}, process::exit};
 
-use crate::lex::{Token, TokenType::{self, *}};
+use crate::{bigint::BigInt, lex::{Token, TokenType::{self, *}}};
 
 #[derive(Debug, Clone)]
 pub struct FunctionDecleration {
     pub name: String,
-    pub args: Vec<String>,
+    pub args: Vec<Param>,
+    /// `-> Type` after the parameters
+    pub return_type: Option<String>,
     pub body: Vec<ASTree>,
     pub position: (usize, u64, u64),
+    /// Declared with `test func`, so `beetle test` runs it
+    pub is_test: bool,
+}
+
+/// A function parameter, optionally annotated like `a: Int`
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub annotation: Option<String>,
+    /// `ref name` shares the caller's variable, so assigning to it changes the caller's variable too
+    pub by_ref: bool,
+}
+
+/// `enum Name { A, B(value), C { field } }`
+#[derive(Debug, Clone)]
+pub struct EnumDecleration {
+    pub name: String,
+    pub variants: Vec<(String, VariantFields)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariantFields {
+    Unit,
+    /// The names in `B(value)` are only there to document what goes in each place
+    Tuple(Vec<String>),
+    Struct(Vec<String>),
+}
+
+/// What comes after `Name::Variant` when making one or matching against one
+#[derive(Debug, Clone)]
+pub enum VariantArgs<T> {
+    Unit,
+    Tuple(Vec<T>),
+    Struct(Vec<(String, T)>),
 }
 
 #[derive(Debug, Clone)]
@@ -25,12 +61,16 @@ pub struct ASTree(
 #[derive(Debug, Clone)]
 pub enum ASTreeType {
     Let {
-        variable: String,
+        pattern: Pattern,
+        /// `let x: Type = ...`
+        annotation: Option<String>,
         value: ASTValue,
     },
     Assign {
         variable: String,
-        indexes: Vec<ASTValue>,
+        /// The annotation the variable was declared with, so reassigning it is checked too
+        annotation: Option<String>,
+        indexes: Vec<Index>,
         value: ASTValue,
     },
     Function(Function),
@@ -52,18 +92,96 @@ pub enum ASTreeType {
     Loop {
         body: Vec<ASTree>,
     },
-    For(String, ASTValue, Vec<ASTree>),
+    For(Pattern, ASTValue, Vec<ASTree>),
+    Match(ASTValue, Vec<MatchArm>),
     Return(ASTValue),
+    /// `const NAME = value;`, only allowed at the top level of a file
+    Const {
+        name: String,
+        annotation: Option<String>,
+        value: ASTValue,
+    },
     Break,
     Continue,
 }
 
+/// The names being bound by a `let`, `for` or `match` arm, `(x, y)` and `[first, ..rest]` take a value apart
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Name(String),
+    /// `_` matches anything without binding it
+    Wildcard,
+    /// A literal such as `1`, `"x"` or `none` that the value has to be equal to
+    Value(ASTValue),
+    /// `Int(n)` matches a value of that type against the inner pattern
+    Type(String, Box<Pattern>),
+    Tuple(Vec<Pattern>),
+    List(Vec<Pattern>),
+    /// `..rest` inside a tuple or list pattern takes all the items the other patterns do not, `..` on its own ignores them
+    Rest(Option<String>),
+    /// `Name::Variant`, `Name::Variant(a, b)` or `Name::Variant { field }`
+    Enum(String, String, VariantArgs<Pattern>),
+    /// `{"key": pattern}` matches a Hash with those keys, any other keys are ignored
+    Hash(Vec<(ASTValue, Pattern)>),
+    /// `a | b` matches if either one does
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Adds the names this pattern binds to `names`
+    pub fn names(&self, names: &mut Vec<String>) {
+        match self {
+            Pattern::Name(name) | Pattern::Rest(Some(name)) => names.push(name.to_owned()),
+            Pattern::Wildcard | Pattern::Value(_) | Pattern::Rest(None) => {},
+            Pattern::Type(_, inner) => inner.names(names),
+            Pattern::Tuple(patterns) | Pattern::List(patterns) | Pattern::Or(patterns) => patterns.iter().for_each(|p| p.names(names)),
+            Pattern::Enum(_, _, args) => match args {
+                VariantArgs::Unit => {},
+                VariantArgs::Tuple(patterns) => patterns.iter().for_each(|p| p.names(names)),
+                VariantArgs::Struct(fields) => fields.iter().for_each(|(_, p)| p.names(names)),
+            },
+            Pattern::Hash(entries) => entries.iter().for_each(|(_, p)| p.names(names)),
+        }
+    }
+}
+
+/// `pattern if guard => body` in a `match`
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<ASTValue>,
+    pub body: ArmBody,
+}
+
+#[derive(Debug, Clone)]
+pub enum ArmBody {
+    /// `=> { ... }`, only allowed when the `match` is a statement
+    Block(Vec<ASTree>),
+    Value(ASTValue),
+}
+
+/// The bounds of `xs[start:end:step]`, any of which can be left out
+#[derive(Debug, Clone)]
+pub struct Slice {
+    pub start: Option<ASTValue>,
+    pub end: Option<ASTValue>,
+    pub step: Option<ASTValue>,
+}
+
+/// What goes between the brackets of `xs[...]`
+#[derive(Debug, Clone)]
+pub enum Index {
+    Single(ASTValue),
+    Slice(Slice),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Op {
     Addition,
     Subtraction,
     Multiplication,
     Division,
+    Power,
     Equality,
     NotEquality,
     Indexing,
@@ -81,7 +199,8 @@ impl Op {
             Op::Equality | Op::NotEquality | Op::LessThan | Op::GreaterThan => 1,
             Op::Addition | Op::Subtraction => 2,
             Op::Multiplication | Op::Division | Op::Modulus => 3,
-            Op::Indexing => 4,
+            Op::Power => 4,
+            Op::Indexing => 5,
         }
     }
 }
@@ -89,6 +208,7 @@ impl Op {
 #[derive(Debug, Clone)]
 pub enum ASTValue {
     Int(i64),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Char(char),
@@ -96,19 +216,59 @@ pub enum ASTValue {
     Function(Function),
     Variable(String),
     Operation(Box<ASTValue>, Box<ASTValue>, Op),
+    Slice(Box<ASTValue>, Box<Slice>),
     List(Vec<ASTValue>),
-    Hash(HashMap<String, ASTValue>),
+    Tuple(Vec<ASTValue>),
+    Match(Box<ASTValue>, Vec<MatchArm>),
+    /// The enum name, variant name and fields of `Name::Variant(...)`
+    Enum(String, String, VariantArgs<ASTValue>),
+    /// The key and value of each entry in the order they were written
+    Hash(Vec<(ASTValue, ASTValue)>),
+    Set(Vec<ASTValue>),
     None,
 }
 
 pub struct ASTParser {
     tokens: Vec<Token>,
     index: usize,
+    /// The type annotation of each variable in scope, one map per block.
+    /// `None` when the closest declaration of that name had no annotation
+    annotations: Vec<HashMap<String, Option<String>>>,
+    /// The names of the `const`s declared so far in the file
+    constants: HashSet<String>,
 }
 
 impl ASTParser {
     pub fn new(tokens: Vec<Token>) -> Self{
-        ASTParser { tokens, index: 0 }
+        ASTParser { tokens, index: 0, annotations: Vec::new(), constants: HashSet::new() }
+    }
+
+    /// Records the names bound by a pattern in the current block, only a plain name can have an annotation
+    fn declare(&mut self, pattern: &Pattern, annotation: Option<String>) {
+        let Some(block) = self.annotations.last_mut() else {return};
+        if let Pattern::Name(name) = pattern {
+            block.insert(name.to_owned(), annotation);
+            return;
+        }
+        let mut names = Vec::new();
+        pattern.names(&mut names);
+        for name in names {
+            block.insert(name, None);
+        }
+    }
+
+    fn annotation_of(&self, name: &str) -> Option<String> {
+        self.annotations.iter().rev().find_map(|block| block.get(name)).cloned().flatten()
+    }
+
+    /// `: Type`, if there is one
+    fn parse_annotation(&mut self) -> Option<String> {
+        if self.peek(0) != Colon {return None;}
+        self.next();
+        match self.next() {
+            Identifier(name) => Some(name),
+            _ => self.parse_error("Expected type name"),
+        }
     }
 
     fn get_position(&self) -> (usize, u64, u64) {
@@ -136,10 +296,13 @@ impl ASTParser {
         self.index < self.tokens.len()
     }
     
-    pub fn parse_all(&mut self) -> (Vec<String>, Vec<FunctionDecleration>) {
+    /// The imports, functions, enums and global `let` and `const` declarations of a file
+    pub fn parse_all(&mut self) -> (Vec<String>, Vec<FunctionDecleration>, Vec<EnumDecleration>, Vec<ASTree>) {
         let imports = self.parse_imports();
-        let functions = self.parse_functions();
-        (imports, functions)
+        // the scope of the globals, underneath the scopes of each function
+        self.annotations.push(HashMap::new());
+        let (functions, enums, globals) = self.parse_functions();
+        (imports, functions, enums, globals)
     }
     
     fn parse_imports(&mut self) -> Vec<String> {
@@ -162,19 +325,125 @@ impl ASTParser {
         imported_files
     }
     
-    fn parse_functions(&mut self) -> Vec<FunctionDecleration> {
+    fn parse_functions(&mut self) -> (Vec<FunctionDecleration>, Vec<EnumDecleration>, Vec<ASTree>) {
         let mut functions = Vec::new();
+        let mut enums = Vec::new();
+        let mut globals = Vec::new();
         while self.has_more() {
             if let Identifier(name) = self.peek(0) {
                 if name == "func" {
                     functions.push(self.parse_function_decleration());
                 }
+                else if name == "test" && self.peek(1) == Identifier("func".to_owned()) {
+                    self.next();
+                    let mut function = self.parse_function_decleration();
+                    function.is_test = true;
+                    functions.push(function);
+                }
+                else if name == "enum" {
+                    enums.push(self.parse_enum_decleration());
+                }
+                else if name == "let" {
+                    globals.push(self.parse_let());
+                }
+                else if name == "const" {
+                    globals.push(self.parse_const());
+                }
                 else {
                     self.parse_error("Unexpected token in file");
                 }
             }
         }
-        functions
+        (functions, enums, globals)
+    }
+
+    fn parse_const(&mut self) -> ASTree {
+        if self.next() != Identifier("const".to_owned()) {self.parse_error("Expected `const`")}
+        let Identifier(name) = self.next() else {self.parse_error("Expected constant name")};
+        let annotation = self.parse_annotation();
+        if self.next() != Equal {self.parse_error("Expected `=`")}
+        let value = self.parse_value();
+        if self.next() != Semicolon {self.parse_error("Expected `;`")}
+        self.declare(&Pattern::Name(name.clone()), annotation.clone());
+        self.constants.insert(name.clone());
+        self.ast_tree(ASTreeType::Const { name, annotation, value })
+    }
+
+    fn parse_enum_decleration(&mut self) -> EnumDecleration {
+        if self.next() != Identifier("enum".to_owned()) {self.parse_error("Expected `enum` keyword")}
+        let Identifier(name) = self.next() else {self.parse_error("Expected enum name")};
+        if self.next() != LeftCurly {self.parse_error("Expected `{`")}
+        let mut variants = Vec::new();
+        loop {
+            let variant = match self.next() {
+                RightCurly => break,
+                Identifier(variant) => variant,
+                _ => self.parse_error("Expected variant name"),
+            };
+            let fields = match self.peek(0) {
+                LeftParren => {self.next(); VariantFields::Tuple(self.parse_names(RightParren))},
+                LeftCurly => {self.next(); VariantFields::Struct(self.parse_names(RightCurly))},
+                _ => VariantFields::Unit,
+            };
+            if variants.iter().any(|(v, _)| *v == variant) {self.parse_error("Duplicate variant name")}
+            variants.push((variant, fields));
+            if self.peek(0) == Comma {self.next();}
+            else if self.peek(0) != RightCurly {self.parse_error("Expected `,` after variant")}
+        }
+        EnumDecleration { name, variants }
+    }
+
+    /// A comma separated list of names up to and including the closing token
+    fn parse_names(&mut self, close: TokenType) -> Vec<String> {
+        let mut names = Vec::new();
+        loop {
+            if self.peek(0) == close {self.next(); return names;}
+            match self.next() {
+                Identifier(name) => names.push(name),
+                _ => self.parse_error("Expected field name"),
+            }
+            if self.peek(0) == Comma {self.next();}
+            else if self.peek(0) != close {self.parse_error("Expected `,` between field names")}
+        }
+    }
+
+    /// Parses `Name::Variant` followed by `(values)` or `{field: value}` if it has fields
+    fn parse_enum_value(&mut self) -> ASTValue {
+        let Identifier(name) = self.next() else {self.parse_error("Expected enum name")};
+        if self.next() != DoubleColon {self.parse_error("Expected `::`")}
+        let Identifier(variant) = self.next() else {self.parse_error("Expected variant name")};
+        let args = match self.peek(0) {
+            LeftParren => {
+                self.next();
+                let mut values = Vec::new();
+                loop {
+                    if self.peek(0) == RightParren {self.next(); break;}
+                    values.push(self.parse_value());
+                    if self.peek(0) == Comma {self.next();}
+                    else if self.peek(0) != RightParren {self.parse_error("Expected `,` or `)`")}
+                }
+                VariantArgs::Tuple(values)
+            },
+            // `{` can also be the body after a value like in `match Name::A {`, so it has to be followed by `field:`
+            LeftCurly if matches!((self.peek(1), self.peek(2)), (Identifier(_), Colon)) => {
+                self.next();
+                let mut fields = Vec::new();
+                loop {
+                    let field = match self.next() {
+                        RightCurly => break,
+                        Identifier(field) => field,
+                        _ => self.parse_error("Expected field name"),
+                    };
+                    if self.next() != Colon {self.parse_error("Expected `:` after field name")}
+                    fields.push((field, self.parse_value()));
+                    if self.peek(0) == Comma {self.next();}
+                    else if self.peek(0) != RightCurly {self.parse_error("Expected `,` or `}`")}
+                }
+                VariantArgs::Struct(fields)
+            },
+            _ => VariantArgs::Unit,
+        };
+        ASTValue::Enum(name, variant, args)
     }
     
     fn parse_function_decleration(&mut self) -> FunctionDecleration {
@@ -182,21 +451,35 @@ impl ASTParser {
         if let Identifier(name) = self.next() {
             let position = self.get_position();
             let args = self.parse_function_params();
+            let return_type = if self.peek(0) == Arrow {
+                self.next();
+                match self.next() {
+                    Identifier(name) => Some(name),
+                    _ => self.parse_error("Expected return type"),
+                }
+            } else {None};
+            self.annotations.push(args.iter().map(|p| (p.name.clone(), p.annotation.clone())).collect());
             let body = self.parse_fuction_body();
-            return FunctionDecleration { name, args, body, position };
+            self.annotations.pop();
+            FunctionDecleration { name, args, return_type, body, position, is_test: false }
         }
         else {
             self.parse_error("Expected function name");
         }
     }
     
-    fn parse_function_params(&mut self) -> Vec<String> {
+    fn parse_function_params(&mut self) -> Vec<Param> {
         let mut params = Vec::new();
         if self.next() != LeftParren {self.parse_error("Expected `(`")}
         loop {
             if self.peek(0) == RightParren {self.next();return params;}
 
-            if let Identifier(name) = self.next() {params.push(name);}
+            let by_ref = matches!(self.peek(1), Identifier(_)) && self.peek(0) == Identifier("ref".to_owned());
+            if by_ref {self.next();}
+            if let Identifier(name) = self.next() {
+                let annotation = self.parse_annotation();
+                params.push(Param { name, annotation, by_ref });
+            }
             else {self.parse_error("Expected variable name");}
             
             if self.peek(0) == Comma {self.next();}
@@ -210,9 +493,11 @@ impl ASTParser {
     fn parse_fuction_body(&mut self) -> Vec<ASTree> {
         let mut expresions = Vec::new();
         if self.next() != LeftCurly {self.parse_error("Expected `{`")}
+        self.annotations.push(HashMap::new());
         loop {
             if  self.peek(0) == RightCurly {
                 self.next();
+                self.annotations.pop();
                 return expresions;
             }
             expresions.push(self.parse_line());
@@ -227,6 +512,10 @@ impl ASTParser {
                 "while" => return self.parse_while(),
                 "loop" => return self.parse_loop(),
                 "for" => return self.parse_for(),
+                "match" => {
+                    let (value, arms) = self.parse_match(true);
+                    return self.ast_tree(ASTreeType::Match(value, arms));
+                },
                 "break" => return self.parse_break(),
                 "continue" => return self.parse_continue(),
                 "if" => {
@@ -259,27 +548,174 @@ impl ASTParser {
             // indexing
             let mut indexes = Vec::new();
             while self.peek(0) == LeftBracket {
-                self.next();
-                indexes.push(self.parse_value());
-                if self.next() != RightBracket {self.parse_error("Expected `]`")}
+                indexes.push(self.parse_index());
+            }
+            // a local variable of the same name can still be changed
+            let local = self.annotations[1..].iter().any(|block| block.contains_key(&variable));
+            if indexes.is_empty() && !local && self.constants.contains(&variable) {
+                self.parse_error(&format!("Cannot assign to constant `{variable}`"));
             }
             if self.next() != Equal {self.parse_error("Expected `=`")}
             let value = self.parse_value();
             if self.next() != Semicolon {self.parse_error("Expected `;`")}
-            return self.ast_tree(ASTreeType::Assign { variable, indexes, value });
+            let annotation = self.annotation_of(&variable);
+            self.ast_tree(ASTreeType::Assign { variable, annotation, indexes, value })
         }
         else {self.parse_error("Expected variable name")}
     }
     
     fn parse_let(&mut self) -> ASTree {
         if self.next() != Identifier("let".to_owned()) {self.parse_error("Expected `let`")}
-        if let Identifier(variable) = self.next() {
-            if self.next() != Equal {self.parse_error("Expected `=`")}
-            let value = self.parse_value();
-            if self.next() != Semicolon {self.parse_error("Expected `;`")}
-            return self.ast_tree(ASTreeType::Let { variable, value });
+        let pattern = self.parse_pattern();
+        let annotation = self.parse_annotation();
+        if self.next() != Equal {self.parse_error("Expected `=`")}
+        let value = self.parse_value();
+        if self.next() != Semicolon {self.parse_error("Expected `;`")}
+        // declared after the value, which can still use an earlier variable of the same name
+        self.declare(&pattern, annotation.clone());
+        self.ast_tree(ASTreeType::Let { pattern, annotation, value })
+    }
+
+    fn parse_pattern(&mut self) -> Pattern {
+        let first = self.parse_single_pattern();
+        if self.peek(0) != Or {return first;}
+        let mut alternatives = vec![first];
+        while self.peek(0) == Or {
+            self.next();
+            alternatives.push(self.parse_single_pattern());
         }
-        else {self.parse_error("Expected variable name")}
+        Pattern::Or(alternatives)
+    }
+
+    fn parse_single_pattern(&mut self) -> Pattern {
+        match self.next() {
+            Identifier(name) => match name.as_str() {
+                "_" => Pattern::Wildcard,
+                "true" => Pattern::Value(ASTValue::Bool(true)),
+                "false" => Pattern::Value(ASTValue::Bool(false)),
+                "none" => Pattern::Value(ASTValue::None),
+                _ if self.peek(0) == DoubleColon => {
+                    self.next();
+                    let Identifier(variant) = self.next() else {self.parse_error("Expected variant name")};
+                    let args = match self.peek(0) {
+                        LeftParren => {self.next(); VariantArgs::Tuple(self.parse_pattern_items(RightParren))},
+                        LeftCurly => {
+                            self.next();
+                            let mut fields = Vec::new();
+                            loop {
+                                let field = match self.next() {
+                                    RightCurly => break,
+                                    Identifier(field) => field,
+                                    _ => self.parse_error("Expected field name"),
+                                };
+                                // `{field}` is short for `{field: field}`
+                                let pattern = if self.peek(0) == Colon {self.next(); self.parse_pattern()} else {Pattern::Name(field.clone())};
+                                fields.push((field, pattern));
+                                if self.peek(0) == Comma {self.next();}
+                                else if self.peek(0) != RightCurly {self.parse_error("Expected `,` or `}`")}
+                            }
+                            VariantArgs::Struct(fields)
+                        },
+                        _ => VariantArgs::Unit,
+                    };
+                    Pattern::Enum(name, variant, args)
+                },
+                _ if self.peek(0) == LeftParren => {
+                    self.next();
+                    let inner = self.parse_pattern();
+                    if self.next() != RightParren {self.parse_error("Expected `)` after type pattern")}
+                    Pattern::Type(name, Box::new(inner))
+                },
+                _ => Pattern::Name(name),
+            },
+            Int(i) => Pattern::Value(ASTValue::Int(i)),
+            BigIntToken(i) => Pattern::Value(ASTValue::BigInt(i)),
+            Float(f) => Pattern::Value(ASTValue::Float(f)),
+            StringToken(s) => Pattern::Value(ASTValue::String(s)),
+            CharToken(c) => Pattern::Value(ASTValue::Char(c)),
+            Subtraction => match self.next() {
+                Int(i) => Pattern::Value(ASTValue::Int(-i)),
+                // the one negative Int whose magnitude is too large for an Int
+                BigIntToken(i) => Pattern::Value(match (-&i).to_i64() {
+                    Some(i) => ASTValue::Int(i),
+                    None => ASTValue::BigInt(-&i),
+                }),
+                Float(f) => Pattern::Value(ASTValue::Float(-f)),
+                _ => self.parse_error("Expected a number after `-` in pattern"),
+            },
+            LeftParren => Pattern::Tuple(self.parse_pattern_items(RightParren)),
+            LeftBracket => Pattern::List(self.parse_pattern_items(RightBracket)),
+            LeftCurly => {
+                let mut entries = Vec::new();
+                loop {
+                    let key = match self.next() {
+                        RightCurly => break,
+                        Int(i) => ASTValue::Int(i),
+                        StringToken(s) => ASTValue::String(s),
+                        CharToken(c) => ASTValue::Char(c),
+                        _ => self.parse_error("Expected a literal key in hash pattern"),
+                    };
+                    if self.next() != Colon {self.parse_error("Expected `:` after hash key")}
+                    entries.push((key, self.parse_pattern()));
+                    if self.peek(0) == Comma {self.next();}
+                    else if self.peek(0) != RightCurly {self.parse_error("Expected `,` in hash pattern")}
+                }
+                Pattern::Hash(entries)
+            },
+            _ => self.parse_error("Expected variable name or pattern"),
+        }
+    }
+
+    /// Parses `match value { pattern if guard => body, ... }`.
+    /// When it is a statement an arm can be a `{ ... }` block, otherwise each arm is a value
+    fn parse_match(&mut self, statement: bool) -> (ASTValue, Vec<MatchArm>) {
+        if self.next() != Identifier("match".to_owned()) {self.parse_error("Expected `match`")}
+        let value = self.parse_value();
+        if self.next() != LeftCurly {self.parse_error("Expected `{` after match value")}
+        let mut arms = Vec::new();
+        loop {
+            if self.peek(0) == RightCurly {self.next(); break;}
+            let pattern = self.parse_pattern();
+            self.annotations.push(HashMap::new());
+            self.declare(&pattern, None);
+            let guard = if self.peek(0) == Identifier("if".to_owned()) {
+                self.next();
+                Some(self.parse_value())
+            } else {None};
+            if self.next() != FatArrow {self.parse_error("Expected `=>` in match arm")}
+
+            if statement && self.peek(0) == LeftCurly {
+                arms.push(MatchArm { pattern, guard, body: ArmBody::Block(self.parse_fuction_body()) });
+                if self.peek(0) == Comma {self.next();}
+            } else {
+                arms.push(MatchArm { pattern, guard, body: ArmBody::Value(self.parse_value()) });
+                if self.peek(0) == Comma {self.next();}
+                else if self.peek(0) != RightCurly {self.parse_error("Expected `,` after match arm")}
+            }
+            self.annotations.pop();
+        }
+        (value, arms)
+    }
+
+    /// The patterns inside `(...)` or `[...]`, up to and including the closing token
+    fn parse_pattern_items(&mut self, close: TokenType) -> Vec<Pattern> {
+        let mut patterns = Vec::new();
+        loop {
+            if self.peek(0) == close {self.next(); break;}
+            if self.peek(0) == DotDot {
+                self.next();
+                if patterns.iter().any(|p| matches!(p, Pattern::Rest(_))) {self.parse_error("Only one `..` is allowed in a pattern")}
+                match self.peek(0) {
+                    Identifier(name) => {self.next(); patterns.push(Pattern::Rest(Some(name)));},
+                    _ => patterns.push(Pattern::Rest(None)),
+                }
+            } else {
+                patterns.push(self.parse_pattern());
+            }
+            if self.peek(0) == Comma {self.next();}
+            else if self.peek(0) != close {self.parse_error("Expected `,` in pattern")}
+        }
+        patterns
     }
 
     fn parse_if(&mut self) -> ASTree {
@@ -289,7 +725,7 @@ impl ASTParser {
         if self.next() != RightParren {self.parse_error("Expected `)`")}
         let body = self.parse_fuction_body();
 
-        return self.ast_tree(ASTreeType::If { condition, body });
+        self.ast_tree(ASTreeType::If { condition, body })
     }
    
     fn parse_else_if(&mut self) -> ASTree {
@@ -300,14 +736,14 @@ impl ASTParser {
         if self.next() != RightParren {self.parse_error("Expected `)`")}
         let body = self.parse_fuction_body();
 
-        return self.ast_tree(ASTreeType::ElseIf { condition, body });
+        self.ast_tree(ASTreeType::ElseIf { condition, body })
     }
 
     fn parse_else(&mut self) -> ASTree {
         if self.next() != Identifier("else".to_owned()) {self.parse_error("Expected `else`")}
         let body = self.parse_fuction_body();
 
-        return self.ast_tree(ASTreeType::Else { body });
+        self.ast_tree(ASTreeType::Else { body })
     }
     
     fn parse_while(&mut self) -> ASTree {
@@ -317,24 +753,26 @@ impl ASTParser {
         if self.next() != RightParren {self.parse_error("Expected `)`")}
         let body = self.parse_fuction_body();
 
-        return self.ast_tree(ASTreeType::While { condition, body });
+        self.ast_tree(ASTreeType::While { condition, body })
     }
     
     fn parse_loop(&mut self) -> ASTree {
         if self.next() != Identifier("loop".to_owned()) {self.parse_error("Expected `loop`")}
         let body = self.parse_fuction_body();
 
-        return self.ast_tree(ASTreeType::Loop { body });
+        self.ast_tree(ASTreeType::Loop { body })
     }
 
     fn parse_for(&mut self) -> ASTree {
         if self.next() != Identifier("for".to_owned()) {self.parse_error("Expected `for`")}
-        if let Identifier(x) = self.next() {
-            if self.next() != Identifier("in".to_owned()) {self.parse_error("Expected `in`")}
-            let list = self.parse_value();
-            let body = self.parse_fuction_body();
-            return self.ast_tree(ASTreeType::For(x, list, body));
-        } else {self.parse_error("Expected variable name after `for`")}
+        let pattern = self.parse_pattern();
+        if self.next() != Identifier("in".to_owned()) {self.parse_error("Expected `in`")}
+        let list = self.parse_value();
+        self.annotations.push(HashMap::new());
+        self.declare(&pattern, None);
+        let body = self.parse_fuction_body();
+        self.annotations.pop();
+        self.ast_tree(ASTreeType::For(pattern, list, body))
     }
 
     fn parse_return(&mut self) -> ASTree {
@@ -342,21 +780,21 @@ impl ASTParser {
         let value = self.parse_value();
         if self.next() != Semicolon {self.parse_error("Expected `;`")}
 
-        return self.ast_tree(ASTreeType::Return(value));
+        self.ast_tree(ASTreeType::Return(value))
     }
 
     fn parse_break(&mut self) -> ASTree {
         if self.next() != Identifier("break".to_owned()) {self.parse_error("Expected `break`")}
         if self.next() != Semicolon {self.parse_error("Expected `;`")}
 
-        return self.ast_tree(ASTreeType::Break);
+        self.ast_tree(ASTreeType::Break)
     }
 
     fn parse_continue(&mut self) -> ASTree {
         if self.next() != Identifier("continue".to_owned()) {self.parse_error("Expected `continue`")}
         if self.next() != Semicolon {self.parse_error("Expected `;`")}
 
-        return self.ast_tree(ASTreeType::Continue);
+        self.ast_tree(ASTreeType::Continue)
     }
     
     fn parse_function_call(&mut self) -> Function {
@@ -373,6 +811,51 @@ impl ASTParser {
         self.parse_error("expected function name");
     }
     
+    /// Parses the `[...]` after a value, either a single index or a slice like `[a:b:step]`
+    fn parse_index(&mut self) -> Index {
+        if self.next() != LeftBracket {self.parse_error("Expected `[`")}
+        let start = match (self.peek(0), self.peek(1)) {
+            (Colon | DoubleColon, _) => None,
+            // `xs[a::b]` is a slice with a step, not the enum variant `a::b`
+            (Identifier(name), DoubleColon) => {self.next(); Some(ASTValue::Variable(name))},
+            _ => Some(self.parse_value()),
+        };
+        if self.peek(0) == RightBracket {
+            self.next();
+            match start {
+                Some(index) => return Index::Single(index),
+                None => self.parse_error("Expected index"),
+            }
+        }
+        // `::` is a slice with no end
+        if self.peek(0) == DoubleColon {
+            self.next();
+            let step = if self.peek(0) == RightBracket {None} else {Some(self.parse_value())};
+            if self.next() != RightBracket {self.parse_error("Expected `]`")}
+            return Index::Slice(Slice { start, end: None, step });
+        }
+        if self.next() != Colon {self.parse_error("Expected `]` or `:`")}
+        let end = if let Colon | RightBracket = self.peek(0) {None} else {Some(self.parse_value())};
+        let mut step = None;
+        if self.peek(0) == Colon {
+            self.next();
+            if self.peek(0) != RightBracket {step = Some(self.parse_value());}
+        }
+        if self.next() != RightBracket {self.parse_error("Expected `]`")}
+        Index::Slice(Slice { start, end, step })
+    }
+
+    /// Applies any number of `[...]` indexes or slices to a value
+    fn parse_indexing(&mut self, mut value: ASTValue) -> ASTValue {
+        while self.peek(0) == LeftBracket {
+            value = match self.parse_index() {
+                Index::Single(index) => ASTValue::Operation(Box::new(value), Box::new(index), Op::Indexing),
+                Index::Slice(slice) => ASTValue::Slice(Box::new(value), Box::new(slice)),
+            };
+        }
+        value
+    }
+
     fn expect_value(&self, values: &Vec<ASTValue>, operations: &Vec<Op>) {
         if values.len() == operations.len() {return;}
         println!("error in parsing value at {:?}", self.get_position());
@@ -392,6 +875,15 @@ impl ASTParser {
             match self.peek(0) {
                 Identifier(name) => {
                     self.expect_value(&values, &operations);
+                    if name == "match" {
+                        let (value, arms) = self.parse_match(false);
+                        values.push(ASTValue::Match(Box::new(value), arms));
+                        continue;
+                    }
+                    if self.peek(1) == DoubleColon {
+                        values.push(self.parse_enum_value());
+                        continue;
+                    }
                     if name == "true" {self.next();values.push(ASTValue::Bool(true));}
                     else if name == "false" {self.next();values.push(ASTValue::Bool(false));}
                     else if name == "none" {self.next();values.push(ASTValue::None);}
@@ -403,19 +895,19 @@ impl ASTParser {
                         values.push(ASTValue::Variable(name));
                     }
 
-                    // indexing
-                    while self.peek(0) == LeftBracket {
-                        self.next();
-                        let v = values.pop().unwrap();
-                        values.push(ASTValue::Operation(Box::new(v), Box::new(self.parse_value()), Op::Indexing));
-                        if self.next() != RightBracket {self.parse_error("Expected `]`")}
-                    }
+                    let v = values.pop().unwrap();
+                    values.push(self.parse_indexing(v));
                 },
                 Int(i) => {
                     self.expect_value(&values, &operations);
                     self.next();
                     values.push(ASTValue::Int(i));
                 },
+                BigIntToken(i) => {
+                    self.expect_value(&values, &operations);
+                    self.next();
+                    values.push(ASTValue::BigInt(i));
+                },
                 Float(f) => {
                     self.expect_value(&values, &operations);
                     self.next();
@@ -426,13 +918,8 @@ impl ASTParser {
                     self.next();
                     values.push(ASTValue::String(content));
 
-                    // indexing
-                    while self.peek(0) == LeftBracket {
-                        self.next();
-                        let v = values.pop().unwrap();
-                        values.push(ASTValue::Operation(Box::new(v), Box::new(self.parse_value()), Op::Indexing));
-                        if self.next() != RightBracket {self.parse_error("Expected `]`")}
-                    }
+                    let v = values.pop().unwrap();
+                    values.push(self.parse_indexing(v));
                 },
                 CharToken(content) => {
                     self.expect_value(&values, &operations);
@@ -460,6 +947,11 @@ impl ASTParser {
                     self.next();
                     operations.push(Op::Division);
                 },
+                Power => {
+                    self.expect_operation(&values, &operations);
+                    self.next();
+                    operations.push(Op::Power);
+                },
                 And => {
                     self.expect_operation(&values, &operations);
                     self.next();
@@ -472,31 +964,12 @@ impl ASTParser {
                 },
                 LeftParren => {
                     self.expect_value(&values, &operations);
-                    self.next();
-                    values.push(self.parse_value());
-                    self.next();
+                    values.push(self.parse_parens());
                 },
                 LeftCurly => {
-                    if let StringToken(_) = self.peek(1) {
-                        self.expect_value(&values, &operations);
-                    }
-                    else if let RightCurly = self.peek(1) {
-                        self.expect_value(&values, &operations);
-                    } else {break;}
-                    self.next();
-                    
-                    let mut new_hashmap = HashMap::new();
-                    loop {
-                        if let RightCurly = self.peek(0) {self.next(); break;}
-                        if let StringToken(s) = self.next() {
-                            if let Colon = self.next() {
-                                let value = self.parse_value();
-                                new_hashmap.insert(s, value);
-                            }
-                        }
-                        if let Comma = self.peek(0) {self.next();}
-                    }
-                    values.push(ASTValue::Hash(new_hashmap));
+                    // a `{` after a value is the start of a body, e.g. `if x {`
+                    if values.len() != operations.len() {break;}
+                    values.push(self.parse_hash_or_set());
                 }
                 LeftBracket => {
                     self.expect_value(&values, &operations);
@@ -509,7 +982,6 @@ impl ASTParser {
                     }
                     values.push(ASTValue::List(v));
                 },
-                Colon => todo!(),
                 Equal => todo!(),
                 DoubleEqual => {
                     self.expect_operation(&values, &operations);
@@ -536,16 +1008,18 @@ impl ASTParser {
                     self.next();
                     operations.push(Op::GreaterThan);
                 },
-                Semicolon | RightParren | RightCurly | RightBracket | Comma => break,
+                Semicolon | RightParren | RightCurly | RightBracket | Comma | Colon | DotDot | FatArrow | DoubleColon | Arrow => break,
             }
         }
         
         'outer: loop {
-            if operations.len() == 0 {return values[0].clone();}
+            if operations.is_empty() {return values[0].clone();}
             let max = operations.iter().map(|op| op.precidence()).max().unwrap();
-            for i in 0..operations.len() {
+            // `**` groups from the right, so `2 ** 3 ** 2` is `2 ** 9`
+            let order: Box<dyn Iterator<Item = usize>> = if max == Op::Power.precidence() {Box::new((0..operations.len()).rev())} else {Box::new(0..operations.len())};
+            for i in order {
                 if operations[i].precidence() == max {
-                    let new_value = ASTValue::Operation(Box::new(values[i].clone()), Box::new(values[i+1].clone()), operations[i].clone());
+                    let new_value = ASTValue::Operation(Box::new(values[i].clone()), Box::new(values[i+1].clone()), operations[i]);
                     values[i] = new_value;
                     values.remove(i+1);
                     operations.remove(i);
@@ -555,6 +1029,53 @@ impl ASTParser {
         }
     }
     
+    /// Parses `(value)` for grouping, or a tuple if there is a comma: `()`, `(a,)` or `(a, b)`
+    fn parse_parens(&mut self) -> ASTValue {
+        if self.next() != LeftParren {self.parse_error("Expected `(`")}
+        if self.peek(0) == RightParren {self.next(); return ASTValue::Tuple(Vec::new());}
+        let first = self.parse_value();
+        if self.peek(0) != Comma {
+            if self.next() != RightParren {self.parse_error("Expected `)`")}
+            return first;
+        }
+        let mut items = vec![first];
+        while self.peek(0) == Comma {
+            self.next();
+            if self.peek(0) == RightParren {break;}
+            items.push(self.parse_value());
+        }
+        if self.next() != RightParren {self.parse_error("Expected `)`")}
+        ASTValue::Tuple(items)
+    }
+
+    /// Parses `{key: value, ...}` as a Hash or `{item, ...}` as a Set, `{}` is an empty Hash
+    fn parse_hash_or_set(&mut self) -> ASTValue {
+        if self.next() != LeftCurly {self.parse_error("Expected `{`")}
+        if self.peek(0) == RightCurly {self.next(); return ASTValue::Hash(Vec::new());}
+
+        let mut key = self.parse_value();
+        // the first entry decides which one it is
+        let is_hash = self.peek(0) == Colon;
+        let mut entries = Vec::new();
+        let mut items = Vec::new();
+        loop {
+            if is_hash {
+                if self.next() != Colon {self.parse_error("Expected `:` after hash key")}
+                entries.push((key, self.parse_value()));
+            } else {
+                items.push(key);
+            }
+            match self.next() {
+                Comma if self.peek(0) == RightCurly => {self.next(); break;},
+                Comma => {},
+                RightCurly => break,
+                _ => self.parse_error(if is_hash {"Expected `,` or `}` in hash"} else {"Expected `,` or `}` in set"}),
+            }
+            key = self.parse_value();
+        }
+        if is_hash {ASTValue::Hash(entries)} else {ASTValue::Set(items)}
+    }
+
     fn parse_error(&mut self, error: &str) -> ! {
         println!(
             "ERROR {error} at ({}, {})",
@@ -0,0 +1,381 @@
+use std::{cmp::Ordering, fmt::Display, ops::{Add, BitAnd, BitOr, Mul, Neg, Sub}, str::FromStr};
+
+/// An integer of any size, used for Ints that no longer fit in an `i64`.
+///
+/// It is stored as its sign and the base 2^32 digits of its magnitude, least significant first.
+/// There are never any leading zero digits, so zero has no digits and is never negative
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+const BASE: u64 = 1 << 32;
+
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        while digits.last() == Some(&0) {digits.pop();}
+        BigInt { negative: negative && !digits.is_empty(), digits }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {return None;}
+        let magnitude = self.digits.iter().rev().fold(0u64, |total, &d| total << 32 | d as u64);
+        if self.negative {
+            if magnitude <= i64::MAX as u64 + 1 {Some((magnitude as i64).wrapping_neg())} else {None}
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.digits.iter().rev().fold(0.0, |total, &d| total * BASE as f64 + d as f64);
+        if self.negative {-magnitude} else {magnitude}
+    }
+
+    /// The integer part of a Float, or `None` for NaN and infinity
+    pub fn from_f64(value: f64) -> Option<BigInt> {
+        if !value.is_finite() {return None;}
+        let value = value.trunc();
+        if value.abs() < (1u64 << 63) as f64 {return Some((value as i64).into());}
+        // anything this large is a whole number, its mantissa shifted left by its exponent
+        let bits = value.to_bits();
+        let mantissa = bits & ((1 << 52) - 1) | 1 << 52;
+        let exponent = ((bits >> 52) & 0x7ff) as usize - 1075;
+        let magnitude = shift_left(&BigInt::from(mantissa as i64).digits, exponent);
+        Some(BigInt::new(value < 0.0, magnitude))
+    }
+
+    /// The quotient and remainder, rounding towards zero like `i64` does, or `None` when dividing by zero
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {return None;}
+        let (quotient, remainder) = div_rem_magnitude(&self.digits, &other.digits);
+        Some((BigInt::new(self.negative != other.negative, quotient), BigInt::new(self.negative, remainder)))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {result = &result * &base;}
+            exponent >>= 1;
+            if exponent > 0 {base = &base * &base;}
+        }
+        result
+    }
+
+    /// Writes the magnitude in a base from 2 to 36, without a sign
+    pub fn magnitude_string(&self, radix: u32) -> String {
+        if self.is_zero() {return "0".to_owned();}
+        // take off as many digits at a time as fit in a u32
+        let mut chunk = radix;
+        let mut chunk_size = 1;
+        while let Some(next) = chunk.checked_mul(radix) {
+            chunk = next;
+            chunk_size += 1;
+        }
+        let mut chunks = Vec::new();
+        let mut digits = self.digits.clone();
+        while !digits.is_empty() {
+            let (quotient, remainder) = div_rem_small(&digits, chunk);
+            chunks.push(remainder);
+            digits = quotient;
+        }
+        let mut output = String::new();
+        for (i, &c) in chunks.iter().rev().enumerate() {
+            let mut part = Vec::new();
+            let mut c = c;
+            for _ in 0..chunk_size {
+                part.push(std::char::from_digit(c % radix, radix).unwrap());
+                c /= radix;
+            }
+            // only the first chunk can start with zeros that should be left out
+            let part: String = part.into_iter().rev().collect();
+            if i == 0 {output.push_str(part.trim_start_matches('0'));} else {output.push_str(&part);}
+        }
+        output
+    }
+
+    /// The digits of the value in two's complement, `length` digits long
+    fn twos_complement(&self, length: usize) -> Vec<u32> {
+        let mut digits = self.digits.clone();
+        digits.resize(length, 0);
+        if self.negative {
+            // -x is !(x - 1)
+            digits = sub_magnitude(&digits, &[1]);
+            digits.resize(length, 0);
+            for d in &mut digits {*d = !*d;}
+        }
+        digits
+    }
+
+    fn from_twos_complement(mut digits: Vec<u32>) -> BigInt {
+        if digits.last().is_some_and(|&d| d >> 31 == 1) {
+            for d in &mut digits {*d = !*d;}
+            BigInt::new(true, add_magnitude(&digits, &[1]))
+        } else {
+            BigInt::new(false, digits)
+        }
+    }
+
+    fn bitwise(&self, other: &BigInt, f: impl Fn(u32, u32) -> u32) -> BigInt {
+        // one extra digit leaves room for the sign
+        let length = self.digits.len().max(other.digits.len()) + 1;
+        let digits = self.twos_complement(length).into_iter().zip(other.twos_complement(length)).map(|(x, y)| f(x, y)).collect();
+        BigInt::from_twos_complement(digits)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        BigInt::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ();
+
+    /// Reads a decimal integer with an optional sign
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() {return Err(());}
+        let mut magnitude = Vec::new();
+        for c in digits.chars() {
+            let digit = c.to_digit(10).ok_or(())?;
+            magnitude = mul_add_small(&magnitude, 10, digit);
+        }
+        Ok(BigInt::new(negative, magnitude))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negative {f.write_str("-")?;}
+        f.write_str(&self.magnitude_string(10))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.digits, &other.digits),
+            (true, true) => cmp_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitude(&self.digits, &other.digits));
+        }
+        // the sign of the result is the sign of whichever is further from zero
+        match cmp_magnitude(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub_magnitude(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_magnitude(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &x) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &y) in other.digits.iter().enumerate() {
+                let total = digits[i + j] as u64 + x as u64 * y as u64 + carry;
+                digits[i + j] = total as u32;
+                carry = total >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, digits)
+    }
+}
+
+impl BitAnd for &BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |x, y| x & y)
+    }
+}
+
+impl BitOr for &BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |x, y| x | y)
+    }
+}
+
+fn cmp_magnitude(x: &[u32], y: &[u32]) -> Ordering {
+    x.len().cmp(&y.len()).then_with(|| x.iter().rev().cmp(y.iter().rev()))
+}
+
+fn add_magnitude(x: &[u32], y: &[u32]) -> Vec<u32> {
+    let (long, short) = if x.len() >= y.len() {(x, y)} else {(y, x)};
+    let mut digits = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &d) in long.iter().enumerate() {
+        let total = d as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        digits.push(total as u32);
+        carry = total >> 32;
+    }
+    digits.push(carry as u32);
+    digits
+}
+
+/// `x - y`, where `x` is at least as large as `y`
+fn sub_magnitude(x: &[u32], y: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(x.len());
+    let mut borrow = 0i64;
+    for (i, &d) in x.iter().enumerate() {
+        let difference = d as i64 - y.get(i).copied().unwrap_or(0) as i64 - borrow;
+        digits.push(difference as u32);
+        borrow = (difference < 0) as i64;
+    }
+    digits
+}
+
+fn mul_add_small(x: &[u32], multiplier: u32, addend: u32) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(x.len() + 1);
+    let mut carry = addend as u64;
+    for &d in x {
+        let total = d as u64 * multiplier as u64 + carry;
+        digits.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry != 0 {digits.push(carry as u32);}
+    digits
+}
+
+fn div_rem_small(x: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0; x.len()];
+    let mut remainder = 0u64;
+    for (i, &d) in x.iter().enumerate().rev() {
+        let current = remainder << 32 | d as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    while quotient.last() == Some(&0) {quotient.pop();}
+    (quotient, remainder as u32)
+}
+
+fn shift_left(x: &[u32], bits: usize) -> Vec<u32> {
+    let mut digits = vec![0; bits / 32];
+    let bits = bits % 32;
+    if bits == 0 {
+        digits.extend_from_slice(x);
+        return digits;
+    }
+    let mut carry = 0;
+    for &d in x {
+        digits.push(d << bits | carry);
+        carry = d >> (32 - bits);
+    }
+    digits.push(carry);
+    digits
+}
+
+/// Long division of magnitudes, following algorithm D from Knuth's The Art of Computer Programming
+fn div_rem_magnitude(x: &[u32], y: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(x, y) == Ordering::Less {return (Vec::new(), x.to_vec());}
+    if y.len() == 1 {
+        let (quotient, remainder) = div_rem_small(x, y[0]);
+        return (quotient, vec![remainder]);
+    }
+
+    // shifting both so the top digit of the divisor is large makes the guesses below at most two too big
+    let shift = y.last().unwrap().leading_zeros() as usize;
+    let divisor = shift_left(y, shift);
+    let divisor = &divisor[..y.len()];
+    let mut remainder = shift_left(x, shift);
+    remainder.resize(x.len() + 1, 0);
+
+    let n = divisor.len();
+    let mut quotient = vec![0; x.len() + 1 - n];
+    for j in (0..quotient.len()).rev() {
+        let top = (remainder[j + n] as u64) << 32 | remainder[j + n - 1] as u64;
+        let mut guess = top / divisor[n - 1] as u64;
+        let mut guess_remainder = top % divisor[n - 1] as u64;
+        while guess >= BASE || guess * divisor[n - 2] as u64 > (guess_remainder << 32 | remainder[j + n - 2] as u64) {
+            guess -= 1;
+            guess_remainder += divisor[n - 1] as u64;
+            if guess_remainder >= BASE {break;}
+        }
+
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = guess * divisor[i] as u64 + carry;
+            carry = product >> 32;
+            let difference = remainder[i + j] as i64 - borrow - (product & 0xffff_ffff) as i64;
+            remainder[i + j] = difference as u32;
+            borrow = (difference < 0) as i64;
+        }
+        let difference = remainder[j + n] as i64 - borrow - carry as i64;
+        remainder[j + n] = difference as u32;
+
+        // the guess was one too big, so add the divisor back
+        if difference < 0 {
+            guess -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let total = remainder[i + j] as u64 + divisor[i] as u64 + carry;
+                remainder[i + j] = total as u32;
+                carry = total >> 32;
+            }
+            remainder[j + n] = remainder[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = guess as u32;
+    }
+
+    remainder.truncate(n);
+    if shift != 0 {
+        for i in 0..n {
+            remainder[i] = remainder[i] >> shift | remainder.get(i + 1).map_or(0, |&d| d << (32 - shift));
+        }
+    }
+    (quotient, remainder)
+}
@@ -0,0 +1,63 @@
+//! Checks on the arguments passed to built-in functions.
+
+use std::ops::RangeBounds;
+
+use crate::bigint::BigInt;
+
+use super::{interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, variables::BeetleString, VarRef, VarType, Variable};
+
+pub(super) fn arg_count(args: &[VarRef], count: usize, position: (usize, u64, u64)) -> InterpResult<()> {
+    arg_count_in(args, count..=count, position)
+}
+
+/// For built-ins with optional arguments, or any number of them
+pub(super) fn arg_count_in(args: &[VarRef], counts: impl RangeBounds<usize>, position: (usize, u64, u64)) -> InterpResult<()> {
+    if counts.contains(&args.len()) {Ok(())} else {Err(InterpError(position, IncorrectArgs))}
+}
+
+pub(super) fn expect_int(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<i64> {
+    match *arg.borrow() {
+        Variable::Int(i) => Ok(i),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+    }
+}
+
+/// An Int of any size
+pub(super) fn expect_big_int(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<BigInt> {
+    match *arg.borrow() {
+        Variable::Int(i) => Ok(i.into()),
+        Variable::BigInt(ref i) => Ok(i.clone()),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+    }
+}
+
+/// An Int or Float as a Float
+pub(super) fn expect_number(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<f64> {
+    match *arg.borrow() {
+        Variable::Int(i) => Ok(i as f64),
+        Variable::BigInt(ref i) => Ok(i.to_f64()),
+        Variable::Float(f) => Ok(f),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Float, x.to_type()))),
+    }
+}
+
+pub(super) fn expect_string(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<BeetleString> {
+    match *arg.borrow() {
+        Variable::String(ref s) => Ok(s.clone()),
+        ref x => Err(InterpError(position, IncorrectType(VarType::String, x.to_type()))),
+    }
+}
+
+pub(super) fn expect_bool(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<bool> {
+    match *arg.borrow() {
+        Variable::Bool(b) => Ok(b),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Bool, x.to_type()))),
+    }
+}
+
+pub(super) fn expect_char(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<char> {
+    match *arg.borrow() {
+        Variable::Char(c) => Ok(c),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Char, x.to_type()))),
+    }
+}
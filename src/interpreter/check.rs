@@ -0,0 +1,524 @@
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+
+use crate::ast::{ASTValue, ASTree, ASTreeType, ArmBody, EnumDecleration, Function, FunctionDecleration, Index, MatchArm, Op, Pattern, Slice, VariantArgs};
+
+use super::{interpreter_error::{InterpError, InterpErrorType::*}, operations, variables::{BeetleHash, BeetleSet, EnumValue}, VarRef, VarType, Variable};
+
+/// The type of a value as far as the checker can tell, `None` when it could be anything
+type Known = Option<VarType>;
+
+/// Looks for type errors without running anything.
+///
+/// The types of local variables come from their annotations, or from the value they are
+/// first given if they are never reassigned. Only mismatches that would be an error every
+/// time the code runs are reported, anything the checker cannot work out is allowed.
+/// The top level declarations of each file are checked like a function body that runs before `main`.
+pub fn check_types(functions: &[FunctionDecleration], enums: &[EnumDecleration], globals: &[Vec<ASTree>]) -> Vec<InterpError> {
+    let mut checker = TypeChecker { functions, enums, returns: HashMap::new(), errors: Vec::new() };
+    // a function called while setting up the globals could give any of them a new value
+    let mut reassigned = HashSet::new();
+    for body in functions.iter().map(|f| &f.body).chain(globals) {
+        reassigned_names(body, functions, &mut reassigned);
+    }
+    let mut context = Context { function: None, scopes: Vec::new(), reassigned, return_type: None, returned: Vec::new() };
+    // each file has its own globals
+    for file in globals {
+        checker.check_body(file, &mut context);
+    }
+    for index in 0..functions.len() {
+        checker.check_function(index);
+    }
+    checker.errors.sort_by_key(|e| e.0);
+    checker.errors
+}
+
+/// Reports every annotation that does not name a built-in type or an enum, even in code that never runs
+pub fn check_annotations(functions: &[FunctionDecleration], enums: &[EnumDecleration], globals: &[Vec<ASTree>]) -> Vec<InterpError> {
+    let checker = TypeChecker { functions, enums, returns: HashMap::new(), errors: Vec::new() };
+    let mut annotations = Vec::new();
+    for function in functions {
+        for name in function.args.iter().map(|p| &p.annotation).chain([&function.return_type]).flatten() {
+            annotations.push((name, function.position));
+        }
+        body_annotations(&function.body, &mut annotations);
+    }
+    for file in globals {
+        body_annotations(file, &mut annotations);
+    }
+    let mut errors: Vec<InterpError> = annotations.into_iter()
+        .filter(|(name, _)| checker.named_type(name).is_none())
+        .map(|(name, position)| InterpError(position, UnknownType(name.to_owned())))
+        .collect();
+    errors.sort_by_key(|e| e.0);
+    errors
+}
+
+/// The annotations of the variables declared or assigned in a body, including in nested blocks
+fn body_annotations<'a>(body: &'a [ASTree], annotations: &mut Vec<(&'a String, (usize, u64, u64))>) {
+    for ASTree(position, ast) in body {
+        match ast {
+            ASTreeType::Let { annotation: Some(name), .. } | ASTreeType::Const { annotation: Some(name), .. }
+            | ASTreeType::Assign { annotation: Some(name), .. } => annotations.push((name, *position)),
+            ASTreeType::If { body, .. } | ASTreeType::ElseIf { body, .. } | ASTreeType::Else { body }
+            | ASTreeType::While { body, .. } | ASTreeType::Loop { body } | ASTreeType::For(_, _, body) => body_annotations(body, annotations),
+            // only a `match` statement has arms with blocks
+            ASTreeType::Match(_, arms) => for arm in arms {
+                if let ArmBody::Block(body) = &arm.body {body_annotations(body, annotations);}
+            },
+            _ => {},
+        }
+    }
+}
+
+struct TypeChecker<'a> {
+    functions: &'a [FunctionDecleration],
+    enums: &'a [EnumDecleration],
+    /// The return type of each function that has been checked, by its index
+    returns: HashMap<usize, Known>,
+    errors: Vec<InterpError>,
+}
+
+/// What is known inside the function being checked
+struct Context<'a> {
+    /// `None` for the top level declarations
+    function: Option<&'a FunctionDecleration>,
+    scopes: Vec<HashMap<String, Known>>,
+    /// Variables that are given a new value somewhere, so their first value says nothing about their type
+    reassigned: HashSet<String>,
+    return_type: Known,
+    /// The type of each `return`
+    returned: Vec<Known>,
+}
+
+impl Context<'_> {
+    fn declare(&mut self, name: &str, var_type: Known) {
+        let var_type = if self.reassigned.contains(name) {None} else {var_type};
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), var_type);
+    }
+
+    fn lookup(&self, name: &str) -> Known {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned().flatten()
+    }
+}
+
+/// The names of all the variables assigned to or passed to a `ref` parameter in a body, including in nested blocks
+fn reassigned_names(body: &[ASTree], functions: &[FunctionDecleration], names: &mut HashSet<String>) {
+    for ASTree(_, ast) in body {
+        match ast {
+            ASTreeType::Let { value, .. } | ASTreeType::Const { value, .. } | ASTreeType::Return(value) => passed_by_ref(value, functions, names),
+            ASTreeType::Assign { variable, indexes, value, .. } => {
+                if indexes.is_empty() {names.insert(variable.to_owned());}
+                for index in indexes {
+                    match index {
+                        Index::Single(value) => passed_by_ref(value, functions, names),
+                        Index::Slice(slice) => slice_passed_by_ref(slice, functions, names),
+                    }
+                }
+                passed_by_ref(value, functions, names);
+            },
+            ASTreeType::Function(function) => call_passed_by_ref(function, functions, names),
+            ASTreeType::If { condition, body } | ASTreeType::ElseIf { condition, body } | ASTreeType::While { condition, body }
+            | ASTreeType::For(_, condition, body) => {
+                passed_by_ref(condition, functions, names);
+                reassigned_names(body, functions, names);
+            },
+            ASTreeType::Else { body } | ASTreeType::Loop { body } => reassigned_names(body, functions, names),
+            ASTreeType::Match(value, arms) => {
+                passed_by_ref(value, functions, names);
+                arms_passed_by_ref(arms, functions, names);
+            },
+            ASTreeType::Break | ASTreeType::Continue => {},
+        }
+    }
+}
+
+/// Adds the variables given to `ref` parameters anywhere in a value
+fn passed_by_ref(value: &ASTValue, functions: &[FunctionDecleration], names: &mut HashSet<String>) {
+    match value {
+        ASTValue::Function(function) => call_passed_by_ref(function, functions, names),
+        ASTValue::Operation(x, y, _) => {
+            passed_by_ref(x, functions, names);
+            passed_by_ref(y, functions, names);
+        },
+        ASTValue::Slice(value, slice) => {
+            passed_by_ref(value, functions, names);
+            slice_passed_by_ref(slice, functions, names);
+        },
+        ASTValue::List(items) | ASTValue::Tuple(items) | ASTValue::Set(items)
+        | ASTValue::Enum(_, _, VariantArgs::Tuple(items)) => for item in items {passed_by_ref(item, functions, names)},
+        ASTValue::Enum(_, _, VariantArgs::Struct(fields)) => for (_, value) in fields {passed_by_ref(value, functions, names)},
+        ASTValue::Hash(entries) => for (key, value) in entries {
+            passed_by_ref(key, functions, names);
+            passed_by_ref(value, functions, names);
+        },
+        ASTValue::Match(value, arms) => {
+            passed_by_ref(value, functions, names);
+            arms_passed_by_ref(arms, functions, names);
+        },
+        _ => {},
+    }
+}
+
+fn call_passed_by_ref(Function { name, args }: &Function, functions: &[FunctionDecleration], names: &mut HashSet<String>) {
+    // built-ins are looked for first, like when running, and never take a `ref` parameter
+    let called = functions.iter().find(|f| f.name == *name).filter(|_| built_in_return(name, args.len()).is_none());
+    if let Some(function) = called {
+        for (param, arg) in function.args.iter().zip(args) {
+            if let (true, ASTValue::Variable(variable)) = (param.by_ref, arg) {names.insert(variable.to_owned());}
+        }
+    }
+    for arg in args {
+        passed_by_ref(arg, functions, names);
+    }
+}
+
+fn slice_passed_by_ref(slice: &Slice, functions: &[FunctionDecleration], names: &mut HashSet<String>) {
+    for bound in [&slice.start, &slice.end, &slice.step].into_iter().flatten() {
+        passed_by_ref(bound, functions, names);
+    }
+}
+
+fn arms_passed_by_ref(arms: &[MatchArm], functions: &[FunctionDecleration], names: &mut HashSet<String>) {
+    for arm in arms {
+        if let Some(guard) = &arm.guard {passed_by_ref(guard, functions, names);}
+        match &arm.body {
+            ArmBody::Block(body) => reassigned_names(body, functions, names),
+            ArmBody::Value(value) => passed_by_ref(value, functions, names),
+        }
+    }
+}
+
+/// A value of the type to try operations on, so the checker agrees with the interpreter
+fn sample(var_type: &VarType) -> Option<VarRef> {
+    Some(match var_type {
+        VarType::None => Variable::None,
+        VarType::Bool => Variable::Bool(true),
+        VarType::Int => Variable::Int(1),
+        VarType::Float => Variable::Float(1.0),
+        VarType::Char => Variable::Char('a'),
+        VarType::String => Variable::String("a".into()),
+        VarType::Type => Variable::Type(VarType::Int),
+        VarType::List => Variable::List(vec![Variable::None.into()]),
+        VarType::Tuple => Variable::Tuple(vec![Variable::None.into()]),
+        VarType::Hash => Variable::Hash(BeetleHash::new()),
+        VarType::Set => Variable::Set(BeetleSet::new()),
+        VarType::Enum(name) => Variable::Enum(EnumValue {
+            name: Rc::clone(name),
+            variant: "".into(),
+            fields: Vec::new(),
+            field_names: Rc::new([]),
+            is_tuple: false,
+        }),
+        VarType::Socket => return None,
+    }.into())
+}
+
+/// The return type of a built-in function, the outer `None` when there is no built-in with that name
+fn built_in_return(name: &str, arg_count: usize) -> Option<Known> {
+    Some(match name {
+        "debug" | "print" | "printsl" | "assert" | "assert_eq" | "seed" | "shuffle" | "sleep" | "push" | "insert" | "add" | "discard" => Some(VarType::None),
+        "set" if arg_count == 3 => Some(VarType::None),
+        "set" | "union" | "intersection" | "difference" => Some(VarType::Set),
+        "repr" | "input" | "format_date" | "str" | "format" | "variant" | "json_stringify" | "trim" | "replace"
+        | "join" | "substring" | "repeat" | "pad_left" | "pad_right" => Some(VarType::String),
+        "len" | "find" | "ord" | "gc" | "random_int" | "floor" | "ceil" | "gcd" => Some(VarType::Int),
+        "is_nan" | "contains" | "has_key" | "starts_with" | "ends_with" | "is_digit" | "is_alpha" | "is_space" => Some(VarType::Bool),
+        "range" | "split" | "keys" | "values" | "items" | "chars" | "lines" => Some(VarType::List),
+        "merge" | "gc_stats" | "date" => Some(VarType::Hash),
+        "type" => Some(VarType::Type),
+        "chr" => Some(VarType::Char),
+        "random" | "now" | "monotonic" | "timestamp" | "sqrt" | "exp" | "log" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "pi" | "e" => Some(VarType::Float),
+        "round" if arg_count == 2 => Some(VarType::Float),
+        // these give back different types depending on their arguments, or none when they fail
+        "exit" | "copy" | "choice" | "parse_date" | "abs" | "min" | "max" | "clamp" | "round" | "pow" | "pop" | "remove" | "int" | "float" | "remove_key" | "upper" | "lower" | "json_parse"
+        | "tcp_bind" | "tcp_accept" | "tcp_connect" | "read_line" | "read_bytes" | "read_all" | "write" | "close"
+        | "set_timeout" | "set_nonblocking" | "peer_addr" | "local_addr" | "udp_bind" | "udp_send_to"
+        | "udp_recv_from" | "parse_addr" | "format_addr" | "http_read_request" | "http_write_response"
+        | "http_handle" | "http_serve" | "http_get" | "http_post" => None,
+        _ => return None,
+    })
+}
+
+impl TypeChecker<'_> {
+    /// The type named in an annotation, or `None` if there is no such type
+    fn named_type(&self, name: &str) -> Known {
+        Some(match name {
+            "None" => VarType::None,
+            "Bool" => VarType::Bool,
+            "Int" => VarType::Int,
+            "Float" => VarType::Float,
+            "Char" => VarType::Char,
+            "String" => VarType::String,
+            "Type" => VarType::Type,
+            "List" => VarType::List,
+            "Tuple" => VarType::Tuple,
+            "Hash" => VarType::Hash,
+            "Set" => VarType::Set,
+            "Socket" => VarType::Socket,
+            _ if self.enums.iter().any(|e| e.name == name) => VarType::Enum(name.into()),
+            _ => return None,
+        })
+    }
+
+    /// Like `named_type` but reports annotations that do not name a type
+    fn annotation_type(&mut self, annotation: &Option<String>, position: (usize, u64, u64)) -> Known {
+        let name = annotation.as_ref()?;
+        let var_type = self.named_type(name);
+        if var_type.is_none() {
+            self.errors.push(InterpError(position, UnknownType(name.to_owned())));
+        }
+        var_type
+    }
+
+    /// Reports a value that can never have its annotated type
+    fn expect(&mut self, annotated: &Known, actual: &Known, what: impl FnOnce() -> String, position: (usize, u64, u64)) {
+        if let (Some(annotated), Some(actual)) = (annotated, actual) {
+            if annotated != actual {
+                self.errors.push(InterpError(position, IncorrectTypeFor(what(), annotated.to_string(), actual.clone())));
+            }
+        }
+    }
+
+    /// Checks the function the first time it is asked for and gives its return type.
+    /// The return type is only inferred when every `return` gives the same type and the function ends in one
+    fn check_function(&mut self, index: usize) -> Known {
+        if let Some(known) = self.returns.get(&index) {return known.clone();}
+        // a recursive call finds nothing known about the function yet
+        self.returns.insert(index, None);
+
+        let functions = self.functions;
+        let function = &functions[index];
+        let mut params = HashMap::new();
+        for param in &function.args {
+            params.insert(param.name.clone(), self.annotation_type(&param.annotation, function.position));
+        }
+        let mut reassigned = HashSet::new();
+        reassigned_names(&function.body, functions, &mut reassigned);
+        let return_type = self.annotation_type(&function.return_type, function.position);
+        let mut context = Context { function: Some(function), scopes: vec![params], reassigned, return_type, returned: Vec::new() };
+        self.check_body(&function.body, &mut context);
+
+        let known = if function.return_type.is_some() {
+            context.return_type
+        } else if matches!(function.body.last(), Some(ASTree(_, ASTreeType::Return(_)))) {
+            let first = context.returned[0].clone();
+            if context.returned.iter().all(|r| *r == first) {first} else {None}
+        } else {None};
+        self.returns.insert(index, known.clone());
+        known
+    }
+
+    fn check_body(&mut self, body: &[ASTree], context: &mut Context) {
+        context.scopes.push(HashMap::new());
+        for ASTree(position, ast) in body {
+            let position = *position;
+            match ast {
+                ASTreeType::Let { pattern, annotation, value } => {
+                    let value_type = self.value_type(value, context, position);
+                    let annotated = self.annotation_type(annotation, position);
+                    match pattern {
+                        Pattern::Name(name) => {
+                            self.expect(&annotated, &value_type, || format!("variable `{name}`"), position);
+                            context.declare(name, annotated.or(value_type));
+                        },
+                        _ => {
+                            self.expect(&annotated, &value_type, || "the value being destructured".to_owned(), position);
+                            self.declare_pattern(pattern, None, context);
+                        },
+                    }
+                },
+                ASTreeType::Assign { variable, annotation, indexes, value } => {
+                    for index in indexes {self.index_type(index, context, position);}
+                    let value_type = self.value_type(value, context, position);
+                    if indexes.is_empty() {
+                        let annotated = annotation.as_ref().and_then(|a| self.named_type(a));
+                        self.expect(&annotated, &value_type, || format!("variable `{variable}`"), position);
+                    }
+                },
+                ASTreeType::Function(Function { name, args }) => {self.call_type(name, args, context, position);},
+                ASTreeType::If { condition, body } | ASTreeType::ElseIf { condition, body } | ASTreeType::While { condition, body } => {
+                    self.value_type(condition, context, position);
+                    self.check_body(body, context);
+                },
+                ASTreeType::Else { body } | ASTreeType::Loop { body } => self.check_body(body, context),
+                ASTreeType::For(pattern, value, body) => {
+                    let items = self.value_type(value, context, position);
+                    // the items of a List, Tuple, Hash or Set can be of any type
+                    if let Some(items) = items.filter(|t| !matches!(t, VarType::List | VarType::Tuple | VarType::Hash | VarType::Set)) {
+                        self.errors.push(InterpError(position, IncorrectType(VarType::List, items)));
+                    }
+                    context.scopes.push(HashMap::new());
+                    self.declare_pattern(pattern, None, context);
+                    self.check_body(body, context);
+                    context.scopes.pop();
+                },
+                ASTreeType::Match(value, arms) => {
+                    self.value_type(value, context, position);
+                    self.arm_types(arms, context, position);
+                },
+                ASTreeType::Return(value) => {
+                    let value_type = self.value_type(value, context, position);
+                    let return_type = context.return_type.clone();
+                    let name = context.function.map_or("", |f| &f.name);
+                    self.expect(&return_type, &value_type, || format!("the return value of `{name}`"), position);
+                    context.returned.push(value_type);
+                },
+                // the parser only allows `const` at the top level
+                ASTreeType::Const { name, annotation, value } => {
+                    let value_type = self.value_type(value, context, position);
+                    let annotated = self.annotation_type(annotation, position);
+                    self.expect(&annotated, &value_type, || format!("constant `{name}`"), position);
+                    context.declare(name, annotated.or(value_type));
+                },
+                ASTreeType::Break | ASTreeType::Continue => {},
+            }
+        }
+        context.scopes.pop();
+    }
+
+    /// Adds the names in a pattern to the current scope, `var_type` is the type of a pattern that is just a name
+    fn declare_pattern(&mut self, pattern: &Pattern, var_type: Known, context: &mut Context) {
+        match pattern {
+            Pattern::Name(name) => context.declare(name, var_type),
+            // `Int(n)` only binds `n` to an Int
+            Pattern::Type(type_name, inner) if matches!(**inner, Pattern::Name(_)) => {
+                let var_type = self.named_type(type_name);
+                self.declare_pattern(inner, var_type, context);
+            },
+            _ => {
+                let mut names = Vec::new();
+                pattern.names(&mut names);
+                for name in names {
+                    context.declare(&name, None);
+                }
+            },
+        }
+    }
+
+    /// Checks each arm and gives the type of their values if they all agree
+    fn arm_types(&mut self, arms: &[MatchArm], context: &mut Context, position: (usize, u64, u64)) -> Known {
+        let mut types = Vec::new();
+        for arm in arms {
+            context.scopes.push(HashMap::new());
+            self.declare_pattern(&arm.pattern, None, context);
+            if let Some(guard) = &arm.guard {self.value_type(guard, context, position);}
+            match &arm.body {
+                ArmBody::Block(body) => self.check_body(body, context),
+                ArmBody::Value(value) => types.push(self.value_type(value, context, position)),
+            }
+            context.scopes.pop();
+        }
+        let first = types.first()?.clone();
+        if types.iter().all(|t| *t == first) {first} else {None}
+    }
+
+    fn index_type(&mut self, index: &Index, context: &mut Context, position: (usize, u64, u64)) {
+        match index {
+            Index::Single(value) => {self.value_type(value, context, position);},
+            Index::Slice(slice) => for bound in [&slice.start, &slice.end, &slice.step].into_iter().flatten() {
+                self.value_type(bound, context, position);
+            },
+        }
+    }
+
+    fn call_type(&mut self, name: &str, args: &[ASTValue], context: &mut Context, position: (usize, u64, u64)) -> Known {
+        let arg_types: Vec<Known> = args.iter().map(|a| self.value_type(a, context, position)).collect();
+        // built-ins are looked for first, like when running
+        if let Some(known) = built_in_return(name, args.len()) {return known;}
+        let functions = self.functions;
+        let index = functions.iter().position(|f| f.name == name)?;
+        let function = &functions[index];
+        if function.args.len() != args.len() {
+            self.errors.push(InterpError(position, IncorrectArgs));
+            return None;
+        }
+        for (param, arg_type) in function.args.iter().zip(&arg_types) {
+            let annotated = param.annotation.as_ref().and_then(|a| self.named_type(a));
+            self.expect(&annotated, arg_type, || format!("parameter `{}` of `{name}`", param.name), position);
+        }
+        self.check_function(index)
+    }
+
+    fn value_type(&mut self, value: &ASTValue, context: &mut Context, position: (usize, u64, u64)) -> Known {
+        match value {
+            ASTValue::Int(_) | ASTValue::BigInt(_) => Some(VarType::Int),
+            ASTValue::Float(_) => Some(VarType::Float),
+            ASTValue::String(_) => Some(VarType::String),
+            ASTValue::Char(_) => Some(VarType::Char),
+            ASTValue::Bool(_) => Some(VarType::Bool),
+            ASTValue::None => Some(VarType::None),
+            ASTValue::Function(Function { name, args }) => self.call_type(name, args, context, position),
+            ASTValue::Variable(name) => context.lookup(name),
+            ASTValue::Operation(x, y, op) => {
+                let x = self.value_type(x, context, position);
+                let y = self.value_type(y, context, position);
+                let (Some(x), Some(y)) = (x, y) else {
+                    // comparisons give a Bool whatever they compare
+                    return matches!(op, Op::Equality | Op::NotEquality | Op::LessThan | Op::GreaterThan).then_some(VarType::Bool);
+                };
+                let (Some(x_sample), Some(y_sample)) = (sample(&x), sample(&y)) else {return None};
+                // a negative power of an Int is a Float
+                if *op == Op::Power && x == VarType::Int && y == VarType::Int {return None;}
+                if *op == Op::Indexing {
+                    return match operations::indexing(&x_sample, &y_sample) {
+                        Err(NoOperation(..)) => {
+                            self.errors.push(InterpError(position, NoOperation(x, y, *op)));
+                            None
+                        },
+                        // only a String is known to give the same type for every index
+                        _ => (x == VarType::String).then_some(VarType::Char),
+                    };
+                }
+                match operations::variable_operation(x_sample, y_sample, *op) {
+                    Ok(result) => Some(result.borrow().to_type()),
+                    Err(e) => {
+                        self.errors.push(InterpError(position, e));
+                        None
+                    },
+                }
+            },
+            ASTValue::Slice(value, slice) => {
+                let value_type = self.value_type(value, context, position)?;
+                for bound in [&slice.start, &slice.end, &slice.step].into_iter().flatten() {
+                    self.value_type(bound, context, position);
+                }
+                match operations::slice(&sample(&value_type)?, None, None, None) {
+                    Ok(_) => Some(value_type),
+                    Err(e) => {
+                        self.errors.push(InterpError(position, e));
+                        None
+                    },
+                }
+            },
+            ASTValue::List(items) | ASTValue::Tuple(items) | ASTValue::Set(items) => {
+                for item in items {self.value_type(item, context, position);}
+                Some(match value {
+                    ASTValue::List(_) => VarType::List,
+                    ASTValue::Tuple(_) => VarType::Tuple,
+                    _ => VarType::Set,
+                })
+            },
+            ASTValue::Hash(entries) => {
+                for (key, value) in entries {
+                    self.value_type(key, context, position);
+                    self.value_type(value, context, position);
+                }
+                Some(VarType::Hash)
+            },
+            // `lib::NAME` is a global from another file when there is no enum called `lib`, and globals can hold anything
+            ASTValue::Enum(name, _, VariantArgs::Unit) if !self.enums.iter().any(|e| e.name == *name) => None,
+            ASTValue::Enum(name, _, args) => {
+                match args {
+                    VariantArgs::Unit => {},
+                    VariantArgs::Tuple(values) => for value in values {self.value_type(value, context, position);},
+                    VariantArgs::Struct(fields) => for (_, value) in fields {self.value_type(value, context, position);},
+                }
+                Some(VarType::Enum(name.as_str().into()))
+            },
+            ASTValue::Match(value, arms) => {
+                self.value_type(value, context, position);
+                self.arm_types(arms, context, position)
+            },
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::{args::{arg_count, arg_count_in}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, operations::hash_key, variables::{share, BeetleHash, BeetleSet, HashKey}, CodeState, VarRef, VarType, Variable};
+
+/// Runs `f` on the Hash in `arg`, or returns an error if it is not a Hash
+fn with_hash<T>(arg: &VarRef, position: (usize, u64, u64), f: impl FnOnce(&BeetleHash) -> T) -> InterpResult<T> {
+    match *arg.borrow() {
+        Variable::Hash(ref h) => Ok(f(h)),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Hash, x.to_type()))),
+    }
+}
+
+fn with_set<T>(arg: &VarRef, position: (usize, u64, u64), f: impl FnOnce(&BeetleSet) -> T) -> InterpResult<T> {
+    match *arg.borrow() {
+        Variable::Set(ref s) => Ok(f(s)),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Set, x.to_type()))),
+    }
+}
+
+impl CodeState {
+    pub(super) fn collection_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "keys" => {
+                arg_count(args, 1, position)?;
+                let keys = with_hash(&args[0], position, |h| h.keys().map(|k| k.to_variable().into()).collect())?;
+                Variable::List(keys).into()
+            }
+            "values" => {
+                arg_count(args, 1, position)?;
+                let values = with_hash(&args[0], position, |h| h.values().map(share).collect())?;
+                Variable::List(values).into()
+            }
+            "items" => {
+                arg_count(args, 1, position)?;
+                // each item is a `(key, value)` tuple
+                let items = with_hash(&args[0], position, |h| {
+                    h.iter().map(|(k, v)| Variable::Tuple(vec![k.to_variable().into(), share(v)]).into()).collect()
+                })?;
+                Variable::List(items).into()
+            }
+            "has_key" => {
+                arg_count(args, 2, position)?;
+                let key = hash_key(&args[1].borrow()).map_err(|e| InterpError(position, e))?;
+                Variable::Bool(with_hash(&args[0], position, |h| h.contains_key(&key))?).into()
+            }
+            "remove_key" => {
+                arg_count(args, 2, position)?;
+                let key = hash_key(&args[1].borrow()).map_err(|e| InterpError(position, e))?;
+                // gives back the value that was removed, or none if the key was not there
+                match *args[0].borrow_mut() {
+                    Variable::Hash(ref mut h) => h.remove(&key).unwrap_or_else(|| Variable::None.into()),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Hash, x.to_type()))),
+                }
+            }
+            "merge" => {
+                arg_count(args, 2, position)?;
+                // a new Hash with the entries of both, the second one wins when they share a key
+                let mut merged = BeetleHash::new();
+                for arg in &args[..2] {
+                    with_hash(arg, position, |h| {
+                        for (k, v) in h.iter() {
+                            merged.insert(k.clone(), share(v));
+                        }
+                    })?;
+                }
+                Variable::Hash(merged).into()
+            }
+            "set" => {
+                arg_count_in(args, 0..=1, position)?;
+                let Some(arg) = args.first() else {return Ok(Some(Variable::Set(BeetleSet::new()).into()))};
+                // anything that can be looped over can become a set
+                let set = match *arg.borrow() {
+                    Variable::List(ref l) => l.iter().map(|v| hash_key(&v.borrow())).collect::<Result<_, _>>().map_err(|e| InterpError(position, e))?,
+                    Variable::String(ref s) => s.chars().map(HashKey::Char).collect(),
+                    Variable::Hash(ref h) => h.keys().cloned().collect(),
+                    Variable::Set(ref s) => s.clone(),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
+                };
+                Variable::Set(set).into()
+            }
+            "add" | "discard" => {
+                arg_count(args, 2, position)?;
+                let item = hash_key(&args[1].borrow()).map_err(|e| InterpError(position, e))?;
+                match *args[0].borrow_mut() {
+                    Variable::Set(ref mut s) if function_name == "add" => {s.insert(item);},
+                    // discarding something that is not in the set does nothing
+                    Variable::Set(ref mut s) => {s.remove(&item);},
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Set, x.to_type()))),
+                }
+                Variable::None.into()
+            }
+            "union" | "intersection" | "difference" => {
+                arg_count(args, 2, position)?;
+                let other = with_set(&args[1], position, BeetleSet::clone)?;
+                let set = with_set(&args[0], position, |s| match function_name {
+                    "union" => s.iter().chain(other.iter()).cloned().collect(),
+                    "intersection" => s.iter().filter(|k| other.contains(k)).cloned().collect(),
+                    _ => s.iter().filter(|k| !other.contains(k)).cloned().collect(),
+                })?;
+                Variable::Set(set).into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
@@ -0,0 +1,59 @@
+use std::rc::Rc;
+
+use crate::ast::{ASTValue, VariantArgs, VariantFields};
+
+use super::{args::arg_count, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, variables::{share, EnumValue}, CodeState, VarRef, VarType, Variable, VariableScope};
+
+impl CodeState {
+    /// Makes `Name::Variant(...)`, checking the variant exists and is given the fields it was declared with
+    pub(super) fn make_enum(&mut self, name: &str, variant: &str, args: &VariantArgs<ASTValue>, local_scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+        let Some(declaration) = self.enums.iter().find(|e| e.name == name) else {
+            return Err(InterpError(position, EnumNotFound(name.to_owned())));
+        };
+        let Some((_, declared)) = declaration.variants.iter().find(|(v, _)| v == variant) else {
+            return Err(InterpError(position, InvalidVariant(format!("{name} has no variant {variant}"))));
+        };
+        let declared = declared.clone();
+
+        let (fields, field_names, is_tuple) = match (&declared, args) {
+            (VariantFields::Unit, VariantArgs::Unit) => (Vec::new(), Vec::new(), false),
+            (VariantFields::Tuple(names), VariantArgs::Tuple(values)) => {
+                if names.len() != values.len() {
+                    return Err(InterpError(position, InvalidVariant(format!("{name}::{variant} takes {} values, got {}", names.len(), values.len()))));
+                }
+                (self.variable_from_asts(values, local_scope, position)?, Vec::new(), true)
+            },
+            (VariantFields::Struct(names), VariantArgs::Struct(values)) => {
+                if let Some((field, _)) = values.iter().find(|(field, _)| !names.contains(field)) {
+                    return Err(InterpError(position, InvalidVariant(format!("{name}::{variant} has no field {field}"))));
+                }
+                // the fields are kept in the order they were declared
+                let mut fields = Vec::new();
+                for field in names {
+                    match values.iter().find(|(f, _)| f == field) {
+                        Some((_, value)) => fields.push(self.variable_from_ast(value, local_scope, position)?),
+                        None => return Err(InterpError(position, InvalidVariant(format!("missing field {field} for {name}::{variant}")))),
+                    }
+                }
+                (fields, names.clone(), false)
+            },
+            (VariantFields::Unit, _) => return Err(InterpError(position, InvalidVariant(format!("{name}::{variant} has no fields")))),
+            (VariantFields::Tuple(_), _) => return Err(InterpError(position, InvalidVariant(format!("{name}::{variant} is made with `{variant}(...)`")))),
+            (VariantFields::Struct(_), _) => return Err(InterpError(position, InvalidVariant(format!("{name}::{variant} is made with `{variant} {{...}}`")))),
+        };
+        let fields = fields.iter().map(share).collect();
+        Ok(Variable::Enum(EnumValue { name: name.into(), variant: variant.into(), fields, field_names: field_names.into(), is_tuple }).into())
+    }
+    pub(super) fn enum_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "variant" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::Enum(ref value) => Variable::String(Rc::clone(&value.variant).as_ref().into()).into(),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Enum("enum".into()), x.to_type()))),
+                }
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
@@ -0,0 +1,209 @@
+use super::{args::{arg_count_in, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, CodeState, VarRef, Variable};
+
+/// A parsed `[[fill]align][sign][#][0][width][.precision][type]` format spec
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    sign: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+/// Widths and precisions past this are almost certainly mistakes, and padding out to them would take a lot of memory
+const MAX_WIDTH: usize = u16::MAX as usize;
+
+/// Reads the digits starting at `i` as a width or precision
+fn parse_count(chars: &[char], i: &mut usize, what: &str, spec: &str) -> Result<usize, String> {
+    let mut count: usize = 0;
+    while let Some(d) = chars.get(*i).and_then(|c| c.to_digit(10)) {
+        count = count.checked_mul(10).and_then(|c| c.checked_add(d as usize)).filter(|&c| c <= MAX_WIDTH)
+            .ok_or_else(|| format!("{what} too large in `{spec}`, it can be at most {MAX_WIDTH}"))?;
+        *i += 1;
+    }
+    Ok(count)
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut result = Spec::default();
+    let mut i = 0;
+
+    if chars.len() >= 2 && matches!(chars[1], '<' | '>' | '^') {
+        result.fill = Some(chars[0]);
+        result.align = Some(chars[1]);
+        i = 2;
+    } else if !chars.is_empty() && matches!(chars[0], '<' | '>' | '^') {
+        result.align = Some(chars[0]);
+        i = 1;
+    }
+    if chars.get(i) == Some(&'+') {result.sign = true; i += 1;}
+    if chars.get(i) == Some(&'#') {result.alternate = true; i += 1;}
+    if chars.get(i) == Some(&'0') {result.zero = true; i += 1;}
+    result.width = parse_count(&chars, &mut i, "width", spec)?;
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let start = i;
+        let precision = parse_count(&chars, &mut i, "precision", spec)?;
+        if start == i {return Err(format!("missing precision after `.` in `{spec}`"));}
+        result.precision = Some(precision);
+    }
+    if let Some(&c) = chars.get(i) {
+        if !matches!(c, 'x' | 'X' | 'b' | 'o' | 'e' | '?') {return Err(format!("unknown format type `{c}` in `{spec}`"));}
+        result.kind = Some(c);
+        i += 1;
+    }
+    if i != chars.len() {return Err(format!("invalid format spec `{spec}`"));}
+    Ok(result)
+}
+
+/// Formats a single value, the sign and any `0x` prefix are kept separate so zero padding can go between them and the digits
+fn format_value(value: &Variable, spec: &Spec) -> Result<(String, String), String> {
+    let sign = |negative: bool| if negative {"-"} else if spec.sign {"+"} else {""}.to_owned();
+    Ok(match (value, spec.kind) {
+        (Variable::Int(i), Some(kind @ ('x' | 'X' | 'b' | 'o'))) => {
+            let magnitude = i.unsigned_abs();
+            let (digits, prefix) = match kind {
+                'x' => (format!("{magnitude:x}"), "0x"),
+                'X' => (format!("{magnitude:X}"), "0x"),
+                'b' => (format!("{magnitude:b}"), "0b"),
+                _ => (format!("{magnitude:o}"), "0o"),
+            };
+            let prefix = if spec.alternate {prefix} else {""};
+            (sign(*i < 0) + prefix, digits)
+        }
+        (Variable::BigInt(i), Some(kind @ ('x' | 'X' | 'b' | 'o'))) => {
+            let (digits, prefix) = match kind {
+                'x' => (i.magnitude_string(16), "0x"),
+                'X' => (i.magnitude_string(16).to_uppercase(), "0x"),
+                'b' => (i.magnitude_string(2), "0b"),
+                _ => (i.magnitude_string(8), "0o"),
+            };
+            let prefix = if spec.alternate {prefix} else {""};
+            (sign(i.is_negative()) + prefix, digits)
+        }
+        (_, Some('x' | 'X' | 'b' | 'o')) => return Err(format!("cannot format a {} as hex, binary or octal", value.to_type())),
+        (Variable::Int(_) | Variable::BigInt(_) | Variable::Float(_), Some('e')) => {
+            let f = match value {
+                Variable::Int(i) => *i as f64,
+                Variable::BigInt(i) => i.to_f64(),
+                Variable::Float(f) => *f,
+                _ => unreachable!(),
+            };
+            let digits = match spec.precision {
+                Some(p) => format!("{:.*e}", p, f.abs()),
+                None => format!("{:e}", f.abs()),
+            };
+            (sign(f.is_sign_negative() && f != 0.0), digits)
+        }
+        (_, Some('e')) => return Err(format!("cannot format a {} in scientific notation", value.to_type())),
+        (Variable::Int(i), None) => match spec.precision {
+            Some(p) => (sign(*i < 0), format!("{:.*}", p, i.unsigned_abs() as f64)),
+            None => (sign(*i < 0), i.unsigned_abs().to_string()),
+        },
+        (Variable::BigInt(i), None) => match spec.precision {
+            Some(p) => (sign(i.is_negative()), format!("{:.*}", p, i.to_f64().abs())),
+            None => (sign(i.is_negative()), i.magnitude_string(10)),
+        },
+        (Variable::Float(f), None) => {
+            let digits = match spec.precision {
+                Some(p) => format!("{:.*}", p, f.abs()),
+                None => f.abs().to_string(),
+            };
+            (sign(f.is_sign_negative() && *f != 0.0 && !f.is_nan()), digits)
+        }
+        (value, Some('?')) => (String::new(), value.repr()),
+        (Variable::String(s), None) => match spec.precision {
+            // precision cuts strings down to that many characters
+            Some(p) => (String::new(), s.slice(0, p).to_owned()),
+            None => (String::new(), s.to_string()),
+        },
+        (value, _) => (String::new(), value.to_string()),
+    })
+}
+
+fn pad(sign: String, digits: String, spec: &Spec, numeric: bool) -> String {
+    let length = sign.chars().count() + digits.chars().count();
+    if length >= spec.width {return sign + &digits;}
+    let padding = spec.width - length;
+
+    if spec.zero && numeric && spec.align.is_none() {
+        return sign + &"0".repeat(padding) + &digits;
+    }
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    // numbers line up on the right by default, everything else on the left
+    let align = spec.align.unwrap_or(if numeric {'>'} else {'<'});
+    match align {
+        '<' => sign + &digits + &fill.repeat(padding),
+        '^' => fill.repeat(padding / 2) + &sign + &digits + &fill.repeat(padding - padding / 2),
+        _ => fill.repeat(padding) + &sign + &digits,
+    }
+}
+
+/// Fills in the `{}` fields of a format string.
+///
+/// Fields can be empty to take the next positional argument, an index like `{1}`,
+/// or a name like `{name}` which is looked up in a Hash passed as the last argument.
+/// `{{` and `}}` are literal braces.
+fn format_string(template: &str, args: &[VarRef]) -> Result<String, String> {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_positional = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {chars.next(); output.push('{');},
+            '}' if chars.peek() == Some(&'}') => {chars.next(); output.push('}');},
+            '}' => return Err("unmatched `}` in format string".to_owned()),
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => return Err("unmatched `{` in format string".to_owned()),
+                    }
+                }
+                let (name, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let spec = parse_spec(spec)?;
+
+                let value = if name.is_empty() {
+                    next_positional += 1;
+                    args.get(next_positional - 1).ok_or(format!("missing argument {}", next_positional - 1))?.borrow().clone()
+                } else if let Ok(index) = name.parse::<usize>() {
+                    args.get(index).ok_or(format!("missing argument {index}"))?.borrow().clone()
+                } else {
+                    let named = args.last().map(|a| a.borrow().clone());
+                    match named {
+                        Some(Variable::Hash(hash)) => hash.get(&name.into()).ok_or(format!("missing named argument `{name}`"))?.borrow().clone(),
+                        _ => return Err(format!("named argument `{name}` needs a Hash as the last argument")),
+                    }
+                };
+
+                let numeric = matches!(value, Variable::Int(_) | Variable::BigInt(_) | Variable::Float(_));
+                let (sign, digits) = format_value(&value, &spec)?;
+                output.push_str(&pad(sign, digits, &spec, numeric));
+            },
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+impl CodeState {
+    pub(super) fn format_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "format" => {
+                arg_count_in(args, 1.., position)?;
+                match format_string(&expect_string(&args[0], position)?, &args[1..]) {
+                    Ok(s) => Variable::String(s.into()).into(),
+                    Err(message) => return Err(InterpError(position, InvalidFormat(message))),
+                }
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
@@ -0,0 +1,106 @@
+use std::{cell::RefCell, collections::HashMap, rc::{Rc, Weak}};
+
+use super::{VarRef, Variable};
+
+/// Collections only happen once this many values are being tracked
+const MIN_COLLECTION: usize = 10_000;
+
+/// Every List, Tuple, Hash and enum variant that has been made, so reference cycles between them can be found.
+/// Values that are not part of a cycle are still freed by reference counting as soon as they are dropped
+struct Heap {
+    objects: Vec<Weak<RefCell<Variable>>>,
+    /// How many tracked values there can be before the next collection
+    next_collection: usize,
+    collections: usize,
+    collected: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = const {RefCell::new(Heap { objects: Vec::new(), next_collection: MIN_COLLECTION, collections: 0, collected: 0 })};
+}
+
+/// Starts tracking a value if it can be part of a cycle
+pub fn track(value: &VarRef) {
+    if matches!(*value.borrow(), Variable::List(_) | Variable::Tuple(_) | Variable::Hash(_) | Variable::Enum(_)) {
+        HEAP.with(|heap| heap.borrow_mut().objects.push(Rc::downgrade(value)));
+    }
+}
+
+pub fn collection_due() -> bool {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        heap.objects.len() >= heap.next_collection
+    })
+}
+
+/// Frees the values that are only kept alive by reference cycles and gives back how many there were.
+///
+/// A value is garbage when every reference to it comes from another tracked value and none of those
+/// can be reached from a value referenced from anywhere else, such as a variable.
+pub fn collect_cycles() -> usize {
+    let objects: Vec<VarRef> = HEAP.with(|heap| heap.borrow().objects.iter().filter_map(Weak::upgrade).collect());
+    let index: HashMap<*const RefCell<Variable>, usize> = objects.iter().enumerate().map(|(i, o)| (Rc::as_ptr(o), i)).collect();
+
+    // count the references that come from other tracked values
+    let mut internal = vec![0; objects.len()];
+    let mut live = vec![false; objects.len()];
+    for (i, object) in objects.iter().enumerate() {
+        let Ok(value) = object.try_borrow() else {
+            // something is using it right now
+            live[i] = true;
+            continue;
+        };
+        for_each_child(&value, |child| if let Some(&j) = index.get(&Rc::as_ptr(child)) {internal[j] += 1;});
+    }
+
+    // anything with more references than that is referenced from outside, one of them is the one in `objects`
+    let mut stack: Vec<usize> = (0..objects.len()).filter(|&i| live[i] || Rc::strong_count(&objects[i]) - 1 > internal[i]).collect();
+    for &i in &stack {live[i] = true;}
+    while let Some(i) = stack.pop() {
+        let Ok(value) = objects[i].try_borrow() else {continue};
+        for_each_child(&value, |child| if let Some(&j) = index.get(&Rc::as_ptr(child)) {
+            if !live[j] {
+                live[j] = true;
+                stack.push(j);
+            }
+        });
+    }
+
+    // emptying the rest breaks their cycles, so they are freed once `objects` is dropped
+    let mut freed = 0;
+    for (object, live) in objects.iter().zip(live) {
+        if !live {
+            *object.borrow_mut() = Variable::None;
+            freed += 1;
+        }
+    }
+    drop(objects);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.objects.retain(|o| o.strong_count() > 0);
+        heap.next_collection = MIN_COLLECTION.max(heap.objects.len() * 2);
+        heap.collections += 1;
+        heap.collected += freed;
+    });
+    freed
+}
+
+/// Runs `f` on each value held by a List, Tuple, Hash or enum variant
+fn for_each_child(value: &Variable, f: impl FnMut(&VarRef)) {
+    match value {
+        Variable::List(items) | Variable::Tuple(items) => items.iter().for_each(f),
+        Variable::Hash(hash) => hash.values().for_each(f),
+        Variable::Enum(value) => value.fields.iter().for_each(f),
+        _ => {},
+    }
+}
+
+/// The number of tracked values still alive, how many collections have run and how many values they freed
+pub fn stats() -> (usize, usize, usize) {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.objects.retain(|o| o.strong_count() > 0);
+        (heap.objects.len(), heap.collections, heap.collected)
+    })
+}
@@ -0,0 +1,290 @@
+use std::{io::{self, BufRead, BufReader, Read, Write}, net::TcpStream, time::Duration};
+
+use super::{args::{arg_count, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, network::{io_error, Socket}, variables::BeetleHash, CodeState, VarRef, VarType, Variable};
+
+/// How long `http_serve` waits on a client for its request, so one that sends nothing cannot stop the server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The start line, headers and body of an HTTP/1.1 request or response
+struct Message {
+    start_line: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Message {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reads one message from the stream, returns `None` if the stream ended before it started
+fn read_message(reader: &mut impl BufRead, read_to_end: bool) -> io::Result<Option<Message>> {
+    let mut start_line = String::new();
+    if reader.read_line(&mut start_line)? == 0 {return Ok(None);}
+    let start_line = start_line.trim_end().to_owned();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {break;}
+        let line = line.trim_end();
+        if line.is_empty() {break;}
+        match line.split_once(':') {
+            Some((k, v)) => headers.push((k.trim().to_lowercase(), v.trim().to_owned())),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("malformed HTTP header `{line}`"))),
+        }
+    }
+
+    let mut message = Message { start_line, headers, body: Vec::new() };
+    if let Some(length) = message.header("content-length") {
+        let length: u64 = length.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed Content-Length"))?;
+        reader.take(length).read_to_end(&mut message.body)?;
+    } else if read_to_end {
+        reader.read_to_end(&mut message.body)?;
+    }
+    Ok(Some(message))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut output = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => output.push(b' '),
+            b'%' if i + 2 < bytes.len() && bytes[i+1].is_ascii_hexdigit() && bytes[i+2].is_ascii_hexdigit() => {
+                let hex = |b: u8| (b as char).to_digit(16).unwrap() as u8;
+                output.push(hex(bytes[i+1]) * 16 + hex(bytes[i+2]));
+                i += 2;
+            }
+            b => output.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn string_hash(pairs: impl Iterator<Item = (String, String)>) -> VarRef {
+    Variable::Hash(pairs.map(|(k, v)| (k.into(), Variable::String(v.into()).into())).collect()).into()
+}
+
+fn reason_phrase(status: i64) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+/// Turns a request into a Hash of `method`, `path`, `query`, `version`, `headers` and `body`
+fn request_to_hash(message: Message, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+    let parts: Vec<&str> = message.start_line.split(' ').collect();
+    if parts.len() != 3 {
+        return Err(InterpError(position, Io(format!("malformed HTTP request line `{}`", message.start_line))));
+    }
+    let (path, query) = parts[1].split_once('?').unwrap_or((parts[1], ""));
+    let query = query.split('&').filter(|q| !q.is_empty()).map(|q| {
+        let (k, v) = q.split_once('=').unwrap_or((q, ""));
+        (percent_decode(k), percent_decode(v))
+    });
+
+    let mut request = BeetleHash::new();
+    request.insert("method".into(), Variable::String(parts[0].to_owned().into()).into());
+    request.insert("path".into(), Variable::String(percent_decode(path).into()).into());
+    request.insert("query".into(), string_hash(query));
+    request.insert("version".into(), Variable::String(parts[2].to_owned().into()).into());
+    request.insert("headers".into(), string_hash(message.headers.into_iter()));
+    request.insert("body".into(), Variable::String(String::from_utf8_lossy(&message.body).into_owned().into()).into());
+    Ok(Variable::Hash(request).into())
+}
+
+/// Turns a response into a Hash of `status`, `headers` and `body`
+fn response_to_hash(message: Message, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+    let status = message.start_line.split(' ').nth(1).and_then(|s| s.parse().ok());
+    let Some(status) = status else {
+        return Err(InterpError(position, Io(format!("malformed HTTP status line `{}`", message.start_line))));
+    };
+
+    let mut response = BeetleHash::new();
+    response.insert("status".into(), Variable::Int(status).into());
+    response.insert("headers".into(), string_hash(message.headers.into_iter()));
+    response.insert("body".into(), Variable::String(String::from_utf8_lossy(&message.body).into_owned().into()).into());
+    Ok(Variable::Hash(response).into())
+}
+
+/// Builds the bytes of a response from either a String body or a Hash of `status`, `headers` and `body`
+fn build_response(response: &VarRef, position: (usize, u64, u64)) -> InterpResult<Vec<u8>> {
+    let mut status = 200;
+    let mut headers = default_headers();
+    let body = match &*response.borrow() {
+        Variable::String(s) => s.to_string(),
+        Variable::Hash(h) => {
+            if let Some(s) = h.get(&"status".into()) {
+                match *s.borrow() {
+                    Variable::Int(i) => status = i,
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+                }
+            }
+            if let Some(extra) = h.get(&"headers".into()) {
+                match &*extra.borrow() {
+                    Variable::Hash(extra) => for (k, v) in extra.iter() {
+                        let k = k.to_string();
+                        headers.retain(|(name, _)| !name.eq_ignore_ascii_case(&k));
+                        headers.push((k, v.borrow().to_string()));
+                    },
+                    x => return Err(InterpError(position, IncorrectType(VarType::Hash, x.to_type()))),
+                }
+            }
+            match h.get(&"body".into()) {
+                Some(b) => b.borrow().to_string(),
+                None => String::new(),
+            }
+        }
+        x => return Err(InterpError(position, IncorrectType(VarType::Hash, x.to_type()))),
+    };
+    Ok(response_bytes(status, headers, &body))
+}
+
+fn default_headers() -> Vec<(String, String)> {
+    vec![("Content-Type".to_owned(), "text/plain; charset=utf-8".to_owned())]
+}
+
+fn response_bytes(status: i64, headers: Vec<(String, String)>, body: &str) -> Vec<u8> {
+    let mut output = format!("HTTP/1.1 {status} {}\r\n", reason_phrase(status));
+    for (k, v) in headers {
+        output.push_str(&format!("{k}: {v}\r\n"));
+    }
+    output.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    output.push_str(body);
+    output.into_bytes()
+}
+
+/// A response with just a status and its reason phrase as the body
+fn status_response(status: i64) -> Vec<u8> {
+    response_bytes(status, default_headers(), reason_phrase(status))
+}
+
+/// Splits `http://host:port/path` into the address to connect to, the host and the path
+fn parse_url(url: &str, position: (usize, u64, u64)) -> InterpResult<(String, String, String)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(InterpError(position, Io(format!("only http:// urls are supported, got `{url}`"))));
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {host.to_owned()} else {format!("{host}:80")};
+    Ok((address, host.to_owned(), path.to_owned()))
+}
+
+fn http_request(method: &str, url: &str, body: &str, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+    let (address, host, path) = parse_url(url, position)?;
+    let mut stream = TcpStream::connect(address).map_err(|e| io_error(position, e))?;
+    let request = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len());
+    stream.write_all(request.as_bytes()).map_err(|e| io_error(position, e))?;
+
+    match read_message(&mut BufReader::new(stream), true).map_err(|e| io_error(position, e))? {
+        Some(message) => response_to_hash(message, position),
+        None => Err(InterpError(position, Io("connection closed before a response was sent".to_owned()))),
+    }
+}
+
+impl CodeState {
+    /// Accepts one connection on the listener and answers it with the handler in `routes` for its path.
+    /// Problems with the connection only affect that connection: a malformed request is answered with a 400,
+    /// a handler that fails with a 500, a client that is too slow to send its request with a 408, and a client that
+    /// goes away is ignored
+    fn http_handle(&mut self, listener: &VarRef, routes: &VarRef, position: (usize, u64, u64)) -> InterpResult<()> {
+        if !matches!(*routes.borrow(), Variable::Hash(_)) {
+            return Err(InterpError(position, IncorrectType(VarType::Hash, routes.borrow().to_type())));
+        }
+        let accepted = match self.get_socket(listener, position)? {
+            Socket::Listener(listener) => listener.accept(),
+            _ => return Err(InterpError(position, Io("expected a TCP listener".to_owned()))),
+        };
+        let Ok((stream, _addr)) = accepted else {return Ok(())};
+        if stream.set_read_timeout(Some(REQUEST_TIMEOUT)).and(stream.set_write_timeout(Some(REQUEST_TIMEOUT))).is_err() {return Ok(());}
+        let mut reader = BufReader::new(stream);
+
+        let response = match read_message(&mut reader, false) {
+            Ok(Some(message)) => match request_to_hash(message, position) {
+                Ok(request) => self.http_respond(request, routes, position),
+                Err(_) => status_response(400),
+            },
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => status_response(400),
+            // which of these a timeout gives depends on the platform
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => status_response(408),
+            Err(_) => return Ok(()),
+        };
+        let _ = reader.get_mut().write_all(&response);
+        Ok(())
+    }
+    /// Runs the handler for the request, printing the error and answering with a 500 if it fails
+    fn http_respond(&mut self, request: VarRef, routes: &VarRef, position: (usize, u64, u64)) -> Vec<u8> {
+        let handler = {
+            let (Variable::Hash(ref routes), Variable::Hash(ref r)) = (&*routes.borrow(), &*request.borrow()) else {unreachable!()};
+            let (method, path) = (r.get(&"method".into()).unwrap().borrow().to_string(), r.get(&"path".into()).unwrap().borrow().to_string());
+            routes.get(&format!("{method} {path}").into()).or(routes.get(&path.into())).map(|h| h.borrow().to_string())
+        };
+        let response = match handler {
+            Some(name) => self.run_function(&name, &vec![request], position).and_then(|response| build_response(&response, position)),
+            None => Ok(status_response(404)),
+        };
+        response.unwrap_or_else(|error| {
+            eprintln!("{error}");
+            status_response(500)
+        })
+    }
+    pub(super) fn http_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "http_read_request" => {
+                arg_count(args, 1, position)?;
+                let stream = self.get_stream(&args[0], position)?;
+                match read_message(stream, false).map_err(|e| io_error(position, e))? {
+                    Some(message) => request_to_hash(message, position)?,
+                    None => Variable::None.into(),
+                }
+            }
+            "http_write_response" => {
+                arg_count(args, 2, position)?;
+                let response = build_response(&args[1], position)?;
+                let stream = self.get_stream(&args[0], position)?.get_mut();
+                stream.write_all(&response).map_err(|e| io_error(position, e))?;
+                Variable::None.into()
+            }
+            "http_handle" => {
+                arg_count(args, 2, position)?;
+                self.http_handle(&args[0], &args[1], position)?;
+                Variable::None.into()
+            }
+            "http_serve" => {
+                arg_count(args, 2, position)?;
+                loop {
+                    self.http_handle(&args[0], &args[1], position)?;
+                }
+            }
+            "http_get" => {
+                arg_count(args, 1, position)?;
+                http_request("GET", &expect_string(&args[0], position)?, "", position)?
+            }
+            "http_post" => {
+                arg_count(args, 2, position)?;
+                http_request("POST", &expect_string(&args[0], position)?, &args[1].borrow().to_string(), position)?
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
@@ -17,17 +17,85 @@ pub enum InterpErrorType {
     IncorrectArgs,
     NoOperation(VarType, VarType, Op),
     IncorrectType(VarType, VarType),
+    /// What the annotation was on, e.g. "parameter `a`", the annotated type and the type of the value
+    IncorrectTypeFor(String, String, VarType),
+    InvalidSocket(usize),
+    Io(String),
+    /// A message, line and column
+    InvalidJson(String, usize, usize),
+    NoJson(VarType),
+    CyclicValue,
+    InvalidFormat(String),
+    StringTooLong,
+    InvalidChar(i64),
+    /// The index and the length of what was indexed
+    IndexOutOfRange(i64, usize),
+    InvalidSlice(String),
+    Unhashable(VarType),
+    PatternMismatch(String),
+    Immutable(VarType),
+    /// The repr of the value no arm matched
+    NoMatch(String),
+    EnumNotFound(String),
+    InvalidVariant(String),
+    /// An annotation that is not the name of a type
+    UnknownType(String),
+    AssignToConstant(String),
+    /// The name of a `ref` parameter that was not given a variable
+    RefNotVariable(String),
+    /// The low and high ends of a range with nothing in it
+    EmptyRange(i64, i64),
+    /// `/` or `%` with an Int of 0 on the right
+    DivisionByZero,
+    InvalidDate(String),
+    InvalidDuration(f64),
+    /// The message given to `assert`, if there was one
+    AssertionFailed(Option<String>),
+    /// The reprs of the two values given to `assert_eq`
+    NotEqual(String, String),
 }
 
 impl Display for InterpError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "At {:?}: ", self.0)?;
-        match &self.1 {
+        write!(f, "At {:?}: {}", self.0, self.1)
+    }
+}
+
+impl Display for InterpErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
             InterpErrorType::VarNotFound(var) => write!(f, "Cannot find variable: {var}"),
             InterpErrorType::FuncNotFound(func) => write!(f, "Cannot find function: {func}"),
             InterpErrorType::IncorrectArgs => write!(f, "Incorrect arguments passed to function"),
             InterpErrorType::NoOperation(x, y, op) => write!(f, "No operation found for {op:?} of {x} and {y}"),
             InterpErrorType::IncorrectType(t1, t2) => write!(f, "Expected type {t1}, got type {t2}"),
+            InterpErrorType::IncorrectTypeFor(what, t1, t2) => write!(f, "Expected type {t1} for {what}, got type {t2}"),
+            InterpErrorType::InvalidSocket(id) => write!(f, "Socket {id} is not open"),
+            InterpErrorType::Io(error) => write!(f, "IO error: {error}"),
+            InterpErrorType::InvalidJson(message, line, column) => write!(f, "Invalid JSON at line {line}, column {column}: {message}"),
+            InterpErrorType::NoJson(t) => write!(f, "Cannot encode a value of type {t} as JSON"),
+            InterpErrorType::CyclicValue => write!(f, "Value contains itself"),
+            InterpErrorType::InvalidFormat(message) => write!(f, "Invalid format string: {message}"),
+            InterpErrorType::StringTooLong => write!(f, "The String would be too long to fit in memory"),
+            InterpErrorType::InvalidChar(code) => write!(f, "{code} is not a valid character code"),
+            InterpErrorType::IndexOutOfRange(index, length) => write!(f, "Index {index} is out of range for length {length}"),
+            InterpErrorType::InvalidSlice(message) => write!(f, "Invalid slice: {message}"),
+            InterpErrorType::Unhashable(t) => write!(f, "A value of type {t} cannot be a Hash key or in a Set"),
+            InterpErrorType::PatternMismatch(message) => write!(f, "Value does not match the pattern: {message}"),
+            InterpErrorType::Immutable(t) => write!(f, "A value of type {t} cannot be changed"),
+            InterpErrorType::NoMatch(value) => write!(f, "No match arm for value {value}"),
+            InterpErrorType::EnumNotFound(name) => write!(f, "Cannot find enum: {name}"),
+            InterpErrorType::InvalidVariant(message) => write!(f, "Invalid enum variant: {message}"),
+            InterpErrorType::UnknownType(name) => write!(f, "Unknown type: {name}"),
+            InterpErrorType::AssignToConstant(name) => write!(f, "Cannot assign to constant: {name}"),
+            InterpErrorType::RefNotVariable(name) => write!(f, "The ref parameter {name} needs a variable that can be assigned to"),
+            InterpErrorType::EmptyRange(low, high) => write!(f, "There are no numbers from {low} to {high}"),
+            InterpErrorType::DivisionByZero => write!(f, "Cannot divide an Int by zero"),
+            InterpErrorType::InvalidDate(message) => write!(f, "Invalid date: {message}"),
+            InterpErrorType::InvalidDuration(ms) => write!(f, "Cannot sleep for {ms} milliseconds"),
+            InterpErrorType::AssertionFailed(None) => write!(f, "Assertion failed"),
+            InterpErrorType::AssertionFailed(Some(message)) => write!(f, "Assertion failed: {message}"),
+            InterpErrorType::NotEqual(left, right) => write!(f, "Assertion failed, the values are not equal\n  left: {left}\n right: {right}"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,312 @@
+use super::{args::{arg_count, arg_count_in, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, variables::BeetleHash, CodeState, VarRef, VarType, Variable};
+
+/// How many arrays and objects can be inside each other, so deeply nested input is an error instead of
+/// running out of stack
+const MAX_DEPTH: usize = 256;
+
+/// A recursive descent JSON parser that keeps track of the line and column for errors
+struct JsonParser {
+    input: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+    depth: usize,
+}
+
+type JsonResult<T> = Result<T, (String, usize, usize)>;
+
+impl JsonParser {
+    fn new(input: &str) -> Self {
+        JsonParser { input: input.chars().collect(), index: 0, line: 1, column: 1, depth: 0 }
+    }
+    fn error<T>(&self, message: &str) -> JsonResult<T> {
+        Err((message.to_owned(), self.line, self.column))
+    }
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.index).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {self.line += 1; self.column = 1;}
+        else {self.column += 1;}
+        Some(c)
+    }
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.next();
+        }
+    }
+    fn expect(&mut self, expected: char) -> JsonResult<()> {
+        match self.peek() {
+            Some(c) if c == expected => {self.next(); Ok(())},
+            Some(c) => self.error(&format!("expected `{expected}`, found `{c}`")),
+            None => self.error(&format!("expected `{expected}`, found end of input")),
+        }
+    }
+    fn expect_word(&mut self, word: &str, value: Variable) -> JsonResult<VarRef> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {return self.error(&format!("invalid literal, expected `{word}`"));}
+            self.next();
+        }
+        Ok(value.into())
+    }
+    fn parse_document(&mut self) -> JsonResult<VarRef> {
+        self.skip_whitespace();
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if let Some(c) = self.peek() {return self.error(&format!("unexpected `{c}` after value"));}
+        Ok(value)
+    }
+    fn parse_value(&mut self) -> JsonResult<VarRef> {
+        match self.peek() {
+            Some('{' | '[') if self.depth == MAX_DEPTH => self.error(&format!("nested more than {MAX_DEPTH} deep")),
+            Some('{') => {
+                self.depth += 1;
+                let object = self.parse_object();
+                self.depth -= 1;
+                object
+            },
+            Some('[') => {
+                self.depth += 1;
+                let array = self.parse_array();
+                self.depth -= 1;
+                array
+            },
+            Some('"') => Ok(Variable::String(self.parse_string()?.into()).into()),
+            Some('t') => self.expect_word("true", Variable::Bool(true)),
+            Some('f') => self.expect_word("false", Variable::Bool(false)),
+            Some('n') => self.expect_word("null", Variable::None),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some(c) => self.error(&format!("unexpected `{c}`")),
+            None => self.error("unexpected end of input"),
+        }
+    }
+    fn parse_object(&mut self) -> JsonResult<VarRef> {
+        self.expect('{')?;
+        let mut hash = BeetleHash::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {self.next(); return Ok(Variable::Hash(hash).into());}
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {return self.error("expected a string key");}
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            hash.insert(key.into(), self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Variable::Hash(hash).into()),
+                _ => return self.error("expected `,` or `}` in object"),
+            }
+        }
+    }
+    fn parse_array(&mut self) -> JsonResult<VarRef> {
+        self.expect('[')?;
+        let mut list = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {self.next(); return Ok(Variable::List(list).into());}
+        loop {
+            self.skip_whitespace();
+            list.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Variable::List(list).into()),
+                _ => return self.error("expected `,` or `]` in array"),
+            }
+        }
+    }
+    fn parse_hex4(&mut self) -> JsonResult<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(d) => {self.next(); code = code * 16 + d;},
+                None => return self.error("invalid unicode escape"),
+            }
+        }
+        Ok(code)
+    }
+    fn parse_string(&mut self) -> JsonResult<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None => return self.error("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let mut code = self.parse_hex4()?;
+                        // surrogate pairs encode characters outside the basic multilingual plane
+                        if (0xD800..0xDC00).contains(&code) {
+                            if self.next() != Some('\\') || self.next() != Some('u') {return self.error("unpaired surrogate in unicode escape");}
+                            let low = self.parse_hex4()?;
+                            if !(0xDC00..0xE000).contains(&low) {return self.error("unpaired surrogate in unicode escape");}
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => s.push(c),
+                            None => return self.error("unpaired surrogate in unicode escape"),
+                        }
+                    },
+                    _ => return self.error("invalid escape sequence"),
+                },
+                Some(c) if (c as u32) < 0x20 => return self.error("control character in string"),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+    fn parse_number(&mut self) -> JsonResult<VarRef> {
+        let start = self.index;
+        let mut is_float = false;
+        if self.peek() == Some('-') {self.next();}
+        match self.peek() {
+            Some('0') => {self.next();},
+            Some('1'..='9') => while let Some('0'..='9') = self.peek() {self.next();},
+            _ => return self.error("expected a digit"),
+        }
+        if self.peek() == Some('.') {
+            is_float = true;
+            self.next();
+            if !matches!(self.peek(), Some('0'..='9')) {return self.error("expected a digit after `.`");}
+            while let Some('0'..='9') = self.peek() {self.next();}
+        }
+        if let Some('e' | 'E') = self.peek() {
+            is_float = true;
+            self.next();
+            if let Some('+' | '-') = self.peek() {self.next();}
+            if !matches!(self.peek(), Some('0'..='9')) {return self.error("expected a digit in exponent");}
+            while let Some('0'..='9') = self.peek() {self.next();}
+        }
+        let text: String = self.input[start..self.index].iter().collect();
+        if !is_float {
+            if let Ok(i) = text.parse() {return Ok(Variable::Int(i).into());}
+            return Ok(Variable::BigInt(text.parse().unwrap()).into());
+        }
+        Ok(Variable::Float(text.parse().unwrap()).into())
+    }
+}
+
+fn escape_string(s: &str, output: &mut String) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Encodes a value as JSON.
+///
+/// Chars become one character strings, Types become their name as a string, Tuples and Sets become arrays,
+/// and NaN or infinite Floats become `null` as JSON has no way to represent them.
+/// Hash keys that are not Strings are written as strings, e.g. `{1: "a"}` becomes `{"1":"a"}`.
+fn stringify(value: &VarRef, indent: &str, depth: usize, seen: &mut Vec<*const Variable>, output: &mut String, position: (usize, u64, u64)) -> InterpResult<()> {
+    let newline = |output: &mut String, depth: usize| {
+        if !indent.is_empty() {
+            output.push('\n');
+            output.push_str(&indent.repeat(depth));
+        }
+    };
+
+    let pointer = value.as_ptr() as *const Variable;
+    if seen.contains(&pointer) {
+        return Err(InterpError(position, CyclicValue));
+    }
+
+    match &*value.borrow() {
+        Variable::None => output.push_str("null"),
+        Variable::Bool(b) => output.push_str(&b.to_string()),
+        Variable::Int(i) => output.push_str(&i.to_string()),
+        Variable::BigInt(i) => output.push_str(&i.to_string()),
+        Variable::Float(f) if !f.is_finite() => output.push_str("null"),
+        Variable::Float(f) => {
+            let number = f.to_string();
+            output.push_str(&number);
+            // keep a decimal point so the value is read back as a Float
+            if !number.contains(['.', 'e', 'E']) {output.push_str(".0");}
+        },
+        Variable::Char(c) => escape_string(&c.to_string(), output),
+        Variable::String(s) => escape_string(s, output),
+        Variable::Type(t) => escape_string(&t.to_string(), output),
+        Variable::List(list) | Variable::Tuple(list) => {
+            if list.is_empty() {output.push_str("[]"); return Ok(());}
+            seen.push(pointer);
+            output.push('[');
+            for (i, item) in list.iter().enumerate() {
+                if i != 0 {output.push(',');}
+                newline(output, depth + 1);
+                stringify(item, indent, depth + 1, seen, output, position)?;
+            }
+            newline(output, depth);
+            output.push(']');
+            seen.pop();
+        },
+        Variable::Set(set) => {
+            let list = Variable::List(set.iter().map(|k| k.to_variable().into()).collect()).into();
+            stringify(&list, indent, depth, seen, output, position)?;
+        },
+        Variable::Hash(hash) => {
+            if hash.is_empty() {output.push_str("{}"); return Ok(());}
+            seen.push(pointer);
+            output.push('{');
+            for (i, (key, value)) in hash.iter().enumerate() {
+                if i != 0 {output.push(',');}
+                newline(output, depth + 1);
+                escape_string(&key.to_string(), output);
+                output.push(':');
+                if !indent.is_empty() {output.push(' ');}
+                stringify(value, indent, depth + 1, seen, output, position)?;
+            }
+            newline(output, depth);
+            output.push('}');
+            seen.pop();
+        },
+        x => return Err(InterpError(position, NoJson(x.to_type()))),
+    }
+    Ok(())
+}
+
+impl CodeState {
+    pub(super) fn json_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "json_parse" => {
+                arg_count(args, 1, position)?;
+                match JsonParser::new(&expect_string(&args[0], position)?).parse_document() {
+                    Ok(value) => value,
+                    Err((message, line, column)) => return Err(InterpError(position, InvalidJson(message, line, column))),
+                }
+            }
+            "json_stringify" => {
+                arg_count_in(args, 1..=2, position)?;
+                let indent = match args.get(1).map(|i| i.borrow().clone()) {
+                    None | Some(Variable::None) => String::new(),
+                    Some(Variable::Int(i)) => " ".repeat(i.max(0) as usize),
+                    Some(Variable::String(s)) => s.to_string(),
+                    Some(x) => return Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+                };
+                let mut output = String::new();
+                stringify(&args[0], &indent, 0, &mut Vec::new(), &mut output, position)?;
+                Variable::String(output.into()).into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
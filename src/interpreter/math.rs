@@ -0,0 +1,153 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::{ast::Op, bigint::BigInt};
+
+use super::{args::{arg_count, arg_count_in, expect_big_int, expect_number}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, operations::variable_operation, variables::share, CodeState, VarRef, VarType, Variable};
+
+/// Orders two values with `<` and `>`, so Ints and Floats can be compared with each other
+fn compare(x: &VarRef, y: &VarRef, position: (usize, u64, u64)) -> InterpResult<Ordering> {
+    let less = |x: &VarRef, y: &VarRef| match variable_operation(Rc::clone(x), Rc::clone(y), Op::LessThan) {
+        Ok(result) => Ok(result.borrow().to_bool()),
+        Err(e) => Err(InterpError(position, e)),
+    };
+    Ok(if less(x, y)? {Ordering::Less} else if less(y, x)? {Ordering::Greater} else {Ordering::Equal})
+}
+
+/// Rounds a Float to an Int, or gives none for NaN and infinity
+fn float_to_int(f: f64) -> Variable {
+    match BigInt::from_f64(f) {
+        Some(i) => i.into(),
+        None => Variable::None,
+    }
+}
+
+impl CodeState {
+    pub(super) fn math_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        // the functions that take a Float and give back a Float
+        let float_function: Option<fn(f64) -> f64> = match function_name {
+            "sqrt" => Some(f64::sqrt),
+            "exp" => Some(f64::exp),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "asin" => Some(f64::asin),
+            "acos" => Some(f64::acos),
+            "atan" => Some(f64::atan),
+            _ => None,
+        };
+        if let Some(f) = float_function {
+            arg_count(args, 1, position)?;
+            return Ok(Some(Variable::Float(f(expect_number(&args[0], position)?)).into()));
+        }
+
+        Ok(Some(match function_name {
+            "pi" => {
+                arg_count(args, 0, position)?;
+                Variable::Float(std::f64::consts::PI).into()
+            }
+            "e" => {
+                arg_count(args, 0, position)?;
+                Variable::Float(std::f64::consts::E).into()
+            }
+            "abs" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::Int(i) => match i.checked_abs() {
+                        Some(i) => Variable::Int(i),
+                        None => Variable::from(-&BigInt::from(i)),
+                    },
+                    Variable::BigInt(ref i) if i.is_negative() => Variable::from(-i),
+                    Variable::BigInt(ref i) => Variable::BigInt(i.clone()),
+                    Variable::Float(f) => Variable::Float(f.abs()),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Float, x.to_type()))),
+                }.into()
+            }
+            // either `min(a, b, ...)` or `min(list)`
+            "min" | "max" => {
+                let items = match args {
+                    [list] => match *list.borrow() {
+                        Variable::List(ref items) | Variable::Tuple(ref items) => items.clone(),
+                        ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
+                    },
+                    _ => args.to_vec(),
+                };
+                let Some(mut best) = items.first().cloned() else {return Err(InterpError(position, IncorrectArgs))};
+                let wanted = if function_name == "min" {Ordering::Less} else {Ordering::Greater};
+                for item in &items[1..] {
+                    if compare(item, &best, position)? == wanted {best = Rc::clone(item);}
+                }
+                share(&best)
+            }
+            "clamp" => {
+                arg_count(args, 3, position)?;
+                let (value, low, high) = (&args[0], &args[1], &args[2]);
+                let clamped = if compare(value, low, position)? == Ordering::Less {low}
+                    else if compare(value, high, position)? == Ordering::Greater {high}
+                    else {value};
+                share(clamped)
+            }
+            "floor" | "ceil" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::Int(_) | Variable::BigInt(_) => share(&args[0]),
+                    Variable::Float(f) => float_to_int(if function_name == "floor" {f.floor()} else {f.ceil()}).into(),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Float, x.to_type()))),
+                }
+            }
+            // `round(x)` gives the nearest Int, `round(x, digits)` a Float with that many decimal places
+            "round" => {
+                arg_count_in(args, 1..=2, position)?;
+                if let Some(digits) = args.get(1) {
+                    let Variable::Int(digits) = *digits.borrow() else {
+                        return Err(InterpError(position, IncorrectType(VarType::Int, digits.borrow().to_type())));
+                    };
+                    let scale = 10f64.powi(digits.clamp(-308, 308) as i32);
+                    return Ok(Some(Variable::Float((expect_number(&args[0], position)? * scale).round() / scale).into()));
+                }
+                match *args[0].borrow() {
+                    Variable::Int(_) | Variable::BigInt(_) => share(&args[0]),
+                    Variable::Float(f) => float_to_int(f.round()).into(),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Float, x.to_type()))),
+                }
+            }
+            "pow" => {
+                arg_count(args, 2, position)?;
+                variable_operation(Rc::clone(&args[0]), Rc::clone(&args[1]), Op::Power).map_err(|e| InterpError(position, e))?
+            }
+            // the natural log, or `log(x, base)`
+            "log" => {
+                arg_count_in(args, 1..=2, position)?;
+                let x = expect_number(&args[0], position)?;
+                Variable::Float(match args.get(1) {
+                    Some(base) => x.log(expect_number(base, position)?),
+                    None => x.ln(),
+                }).into()
+            }
+            "atan2" => {
+                arg_count(args, 2, position)?;
+                Variable::Float(expect_number(&args[0], position)?.atan2(expect_number(&args[1], position)?)).into()
+            }
+            "is_nan" => {
+                arg_count(args, 1, position)?;
+                Variable::Bool(expect_number(&args[0], position)?.is_nan()).into()
+            }
+            "gcd" => {
+                arg_count(args, 2, position)?;
+                if let (Variable::Int(x), Variable::Int(y)) = (&*args[0].borrow(), &*args[1].borrow()) {
+                    let (mut x, mut y) = (x.unsigned_abs(), y.unsigned_abs());
+                    while y != 0 {(x, y) = (y, x % y);}
+                    // only gcd(i64::MIN, 0) does not fit back in an Int
+                    if let Ok(x) = i64::try_from(x) {return Ok(Some(Variable::Int(x).into()));}
+                }
+                let (mut x, mut y) = (expect_big_int(&args[0], position)?, expect_big_int(&args[1], position)?);
+                while !y.is_zero() {
+                    let remainder = x.div_rem(&y).unwrap().1;
+                    x = y;
+                    y = remainder;
+                }
+                Variable::from(if x.is_negative() {-&x} else {x}).into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
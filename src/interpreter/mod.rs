@@ -1,56 +1,124 @@
-use std::{collections::HashMap, io::{self, BufRead, BufReader, Write}, net::{TcpListener, TcpStream}, ops::DerefMut, process, rc::Rc};
+use std::{collections::{HashMap, HashSet}, io::{self, Write}, process, rc::Rc, time::Instant};
 
+use args::{arg_count, arg_count_in, expect_int, expect_string};
 use interpreter_error::{InterpError, InterpResult, InterpErrorType::*};
-use variables::{deep_copy, VarRef, VarType, Variable};
+use network::Socket;
+use random::Rng;
+use variables::{new_binding, share, BeetleHash, BeetleSet, Binding, HashKey, VarRef, VarType, Variable};
 
-use crate::ast::{ASTValue, ASTree, ASTreeType, Function, FunctionDecleration, Op};
+use crate::{ast::{VariantArgs, ASTValue, ASTree, ASTreeType, ArmBody, EnumDecleration, Function, FunctionDecleration, Index, Op, Pattern, Slice}, bigint::BigInt};
 
+pub mod check;
+mod args;
+mod collections;
+mod enums;
+mod format;
+mod gc;
+mod http;
 mod interpreter_error;
+mod json;
+mod math;
+mod network;
 mod operations;
+mod patterns;
+mod random;
+mod strings;
+pub mod testing;
+mod time;
 mod variables;
 
-type VariableScope = HashMap<String, VarRef>;
+type VariableScope = HashMap<String, Binding>;
 
 fn clone_scope(scope: &VariableScope) -> VariableScope {
     let mut new_scope = VariableScope::new();
     for (name, item) in scope.iter() {
-        new_scope.insert(name.into(), Rc::clone(&item));
+        new_scope.insert(name.into(), Rc::clone(item));
     }
 
     new_scope
 }
 
+/// Checks a value against an optional type annotation, `what` names the thing that was annotated
+fn check_annotation(value: &VarRef, annotation: &Option<String>, what: impl FnOnce() -> String, position: (usize, u64, u64)) -> InterpResult<()> {
+    let Some(annotation) = annotation else {return Ok(())};
+    let var_type = value.borrow().to_type();
+    if var_type.to_string() != *annotation {
+        return Err(InterpError(position, IncorrectTypeFor(what(), annotation.to_owned(), var_type)));
+    }
+    Ok(())
+}
+
 pub struct CodeState {
     functions: Vec<FunctionDecleration>,
-    global_var_scope: VariableScope,
+    enums: Vec<EnumDecleration>,
+    /// The globals declared with `let` or `const` in each file, by file index
+    global_var_scope: Vec<VariableScope>,
+    /// The names of the globals declared with `const`, by file index
+    constants: Vec<HashSet<String>>,
+    /// The name each file can be referred to by, `lib` for `lib.bt`
+    modules: Vec<String>,
+    /// The file of the function being run, its globals are the ones in scope
+    file: usize,
     ret: bool,
     brk: bool,
     con: bool,
-    tcp_listener: Option<TcpListener>,
-    tcp_stream: Option<TcpStream>,
+    /// Open sockets by their handle id, removed when closed
+    sockets: HashMap<usize, Socket>,
+    /// The id the next socket opened is given
+    next_socket: usize,
+    /// Used by `random` and the other random built-ins, `seed` replaces it
+    rng: Rng,
+    /// When the program started, what `monotonic` counts from
+    started: Instant,
 }
 
 impl CodeState {
-    pub fn new(functions: Vec<FunctionDecleration>) -> Self {
-        let global_var_scope = VariableScope::new();
-        return CodeState { functions, global_var_scope, ret: false, brk: false, con: false, tcp_listener: None, tcp_stream: None };
+    pub fn new(functions: Vec<FunctionDecleration>, enums: Vec<EnumDecleration>, modules: Vec<String>) -> Self {
+        let global_var_scope = vec![VariableScope::new(); modules.len()];
+        let constants = vec![HashSet::new(); modules.len()];
+        CodeState { functions, enums, global_var_scope, constants, modules, file: 0, ret: false, brk: false, con: false, sockets: HashMap::new(), next_socket: 0, rng: Rng::from_entropy(), started: Instant::now() }
+    }
+    /// Evaluates the top level `let` and `const` declarations in order
+    pub fn run_globals(&mut self, globals: &[ASTree]) -> InterpResult<()> {
+        for ASTree(position, ast) in globals {
+            let position = *position;
+            self.file = position.0;
+            match ast {
+                ASTreeType::Let { pattern, annotation, value } => {
+                    let value = self.variable_from_ast(value, &VariableScope::new(), position)?;
+                    check_annotation(&value, annotation, || match pattern {
+                        Pattern::Name(name) => format!("variable `{name}`"),
+                        _ => "the value being destructured".to_owned(),
+                    }, position)?;
+                    patterns::bind_pattern(pattern, value, &mut self.global_var_scope[position.0], position)?;
+                },
+                ASTreeType::Const { name, annotation, value } => {
+                    let value = self.variable_from_ast(value, &VariableScope::new(), position)?;
+                    check_annotation(&value, annotation, || format!("constant `{name}`"), position)?;
+                    self.global_var_scope[position.0].insert(name.to_owned(), new_binding(&value));
+                    self.constants[position.0].insert(name.to_owned());
+                },
+                // the parser only allows declarations at the top level
+                _ => unreachable!(),
+            }
+        }
+        self.file = 0;
+        Ok(())
     }
     fn variable_from_ast(&mut self, value: &ASTValue, local_scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<VarRef> {
-        return match value {
+        match value {
             ASTValue::Int(i) => {Ok(Variable::Int(*i).into())}
+            ASTValue::BigInt(i) => Ok(Variable::BigInt(i.clone()).into()),
             ASTValue::Float(f) => {Ok(Variable::Float(*f).into())}
             ASTValue::Bool(bool) => Ok(Variable::Bool(*bool).into()),
             ASTValue::String(content) => Ok(Variable::String(content.to_owned().into()).into()),
             ASTValue::Char(content) => Ok(Variable::Char(*content).into()),
-            ASTValue::Function(Function { name, args }) => {
-                let args = &self.variable_from_asts(&args[..], &local_scope, position)?;
-                self.run_function(name, args, position)
-            },
+            ASTValue::Function(function) => self.call_function(function, local_scope, position),
             ASTValue::Variable(name) => {
                 if local_scope.contains_key(name) {
-                    Ok(local_scope.get(name).unwrap().clone())
-                } else if self.global_var_scope.contains_key(name) {
-                    Ok(self.global_var_scope.get(name).unwrap().clone())
+                    Ok(Rc::clone(&local_scope.get(name).unwrap().borrow()))
+                } else if self.global_var_scope[self.file].contains_key(name) {
+                    Ok(Rc::clone(&self.global_var_scope[self.file].get(name).unwrap().borrow()))
                 } else {
                     Err(InterpError(position, VarNotFound(name.to_owned())))
                 }
@@ -58,44 +126,90 @@ impl CodeState {
             ASTValue::Operation(var1, var2, op) => {
                 let x = &self.variable_from_ast(var1, local_scope, position)?;
                 let y = &self.variable_from_ast(var2, local_scope, position)?;
-                if let Some(v) = operations::variable_operation(Rc::clone(x), Rc::clone(y), *op) {
-                    Ok(v)
-                }
-                else {
-                    Err(InterpError(position, NoOperation(x.borrow().to_type(), y.borrow().to_type(), *op)))
+                if *op == Op::Indexing {
+                    return operations::indexing(x, y).map_err(|e| InterpError(position, e));
                 }
+                operations::variable_operation(Rc::clone(x), Rc::clone(y), *op).map_err(|e| InterpError(position, e))
             },
-            ASTValue::List(vec) => Ok(Variable::List(self.variable_from_asts(&vec, local_scope, position)?).into()),
+            ASTValue::Slice(value, slice) => {
+                let value = self.variable_from_ast(value, local_scope, position)?;
+                let (start, end, step) = self.slice_bounds(slice, local_scope, position)?;
+                operations::slice(&value, start, end, step).map_err(|e| InterpError(position, e))
+            },
+            ASTValue::List(vec) => Ok(Variable::List(self.variable_from_asts(vec, local_scope, position)?.iter().map(share).collect()).into()),
+            ASTValue::Tuple(vec) => Ok(Variable::Tuple(self.variable_from_asts(vec, local_scope, position)?.iter().map(share).collect()).into()),
             ASTValue::Hash(hash) => {
-                let mut new_hash = HashMap::new();
+                let mut new_hash = BeetleHash::new();
 
                 for (k, v) in hash {
-                    new_hash.insert(k.to_owned(), self.variable_from_ast(v, local_scope, position)?);
+                    let key = operations::hash_key(&self.variable_from_ast(k, local_scope, position)?.borrow()).map_err(|e| InterpError(position, e))?;
+                    new_hash.insert(key, share(&self.variable_from_ast(v, local_scope, position)?));
                 }
 
-                Ok(Variable::Hash(Box::new(new_hash)).into())
+                Ok(Variable::Hash(new_hash).into())
+            },
+            ASTValue::Set(items) => {
+                let mut new_set = BeetleSet::new();
+                for item in items {
+                    new_set.insert(operations::hash_key(&self.variable_from_ast(item, local_scope, position)?.borrow()).map_err(|e| InterpError(position, e))?);
+                }
+                Ok(Variable::Set(new_set).into())
+            },
+            // `lib::NAME` is a global from another file, unless there is an enum called `lib`
+            ASTValue::Enum(module, name, VariantArgs::Unit) if !self.enums.iter().any(|e| e.name == *module) && self.modules.contains(module) => {
+                let file = self.modules.iter().position(|m| m == module).unwrap();
+                match self.global_var_scope[file].get(name) {
+                    Some(value) => Ok(Rc::clone(&value.borrow())),
+                    None => Err(InterpError(position, VarNotFound(format!("{module}::{name}")))),
+                }
+            },
+            ASTValue::Enum(name, variant, args) => self.make_enum(name, variant, args, local_scope, position),
+            ASTValue::Match(value, arms) => {
+                let value = self.variable_from_ast(value, local_scope, position)?;
+                match self.find_arm(&value, arms, local_scope, position)? {
+                    (ArmBody::Value(result), arm_scope) => self.variable_from_ast(result, &arm_scope, position),
+                    // the parser only allows blocks in a `match` statement
+                    (ArmBody::Block(_), _) => unreachable!(),
+                }
             },
             ASTValue::None => Ok(Variable::None.into()),
-        };
+        }
+    }
+    fn slice_bounds(&mut self, slice: &Slice, local_scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<(Option<i64>, Option<i64>, Option<i64>)> {
+        let mut bounds = [None; 3];
+        for (bound, value) in bounds.iter_mut().zip([&slice.start, &slice.end, &slice.step]) {
+            if let Some(value) = value {
+                *bound = match *self.variable_from_ast(value, local_scope, position)?.borrow() {
+                    Variable::Int(i) => Some(i),
+                    Variable::None => None,
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+                };
+            }
+        }
+        Ok((bounds[0], bounds[1], bounds[2]))
     }
     fn variable_from_asts(&mut self, values: &[ASTValue], local_scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<Vec<VarRef>> {
         values.iter().map(|v| self.variable_from_ast(v, local_scope, position)).collect()
     }
     fn get_function(&self, name: &str, position: (usize, u64, u64)) -> InterpResult<FunctionDecleration> {
         let valid_functions: Vec<&FunctionDecleration> = self.functions.iter().filter(|f| f.name == name).collect();
-        if valid_functions.len() == 0 {
+        if valid_functions.is_empty() {
             return Err(InterpError(position, FuncNotFound(name.to_owned())));
         }
-        return Ok(valid_functions[0].clone());
+        Ok(valid_functions[0].clone())
     }
     fn built_in_funtion(&mut self, function_name: &str, args: &Vec<VarRef>, position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
         Ok(Some(match function_name {
             "debug" => {
                 for arg in args {
-                    print!("{:p}: {}\n", arg.as_ref(), arg.borrow());
+                    println!("{}", arg.borrow().repr());
                 }
                 Variable::None.into()
             }
+            "repr" => {
+                arg_count(args, 1, position)?;
+                Variable::String(args[0].borrow().repr().into()).into()
+            }
             "print" => {
                 for arg in args {
                     print!("{}", arg.borrow());
@@ -110,292 +224,305 @@ impl CodeState {
                 Variable::None.into()
             }
             "input" => {
-                if args.len() >= 2 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if args.len() == 1 {
-                    print!("{}", args[0].borrow());
+                arg_count_in(args, 0..=1, position)?;
+                if let Some(prompt) = args.first() {
+                    print!("{}", prompt.borrow());
                     io::stdout().flush().unwrap();
                 }
                 let mut input = String::new();
                 std::io::stdin().read_line(&mut input).expect("failed to readline");
-                if input.chars().nth(input.len()-1) == Some('\n') {input.pop();}
-                if input.chars().nth(input.len()-1) == Some('\r') {input.pop();}
+                // at the end of the input nothing is read and this gives an empty String
+                if input.ends_with('\n') {input.pop();}
+                if input.ends_with('\r') {input.pop();}
 
-                Variable::String(input).into()
+                Variable::String(input.into()).into()
             }
             "exit" => {
                 process::exit(0);
             }
             "copy" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 variables::deep_copy(&args[0])
             }
+            "gc" => {
+                arg_count(args, 0, position)?;
+                Variable::Int(gc::collect_cycles() as i64).into()
+            }
+            "gc_stats" => {
+                arg_count(args, 0, position)?;
+                let (live, collections, collected) = gc::stats();
+                let mut stats = BeetleHash::new();
+                stats.insert("live".into(), Variable::Int(live as i64).into());
+                stats.insert("collections".into(), Variable::Int(collections as i64).into());
+                stats.insert("collected".into(), Variable::Int(collected as i64).into());
+                Variable::Hash(stats).into()
+            }
             "push" => {
-                if args.len() != 2 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 2, position)?;
+                let item = share(&args[1]);
                 if let Variable::List(ref mut l) = *args[0].borrow_mut() {
-                    l.push(deep_copy(&args[1]));
+                    l.push(item);
                 } else {return Err(InterpError(position, IncorrectType(VarType::List, args[0].borrow().to_type())));}
 
                 Variable::None.into()
             }
             "pop" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 if let Variable::List(ref mut l) = *args[0].borrow_mut() {
                     return Ok(l.pop());
                 } else {return Err(InterpError(position, IncorrectType(VarType::List, args[0].borrow().to_type())));}
             }
             "insert" => {
-                if args.len() != 3 {
-                    return Err(InterpError(position, IncorrectArgs));
+                arg_count(args, 3, position)?;
+                let index = expect_int(&args[1], position)?;
+                let item = share(&args[2]);
+                match *args[0].borrow_mut() {
+                    Variable::List(ref mut l) => {
+                        // inserting at the length adds to the end
+                        let i = usize::try_from(index).ok().filter(|&i| i <= l.len()).ok_or(InterpError(position, IndexOutOfRange(index, l.len())))?;
+                        l.insert(i, item);
+                    }
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
                 }
-                if let Variable::List(ref mut l) = *args[0].borrow_mut() {
-                    if let Variable::Int(i) = *args[1].borrow_mut() {
-                        l.insert(i as usize, deep_copy(&args[2]));
-                    } else {return Err(InterpError(position, IncorrectType(VarType::Int, args[1].borrow().to_type())));}
-                } else {return Err(InterpError(position, IncorrectType(VarType::List, args[0].borrow().to_type())));}
-
                 Variable::None.into()
             }
             "remove" => {
-                if args.len() != 2 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::List(ref mut l) = *args[0].borrow_mut() {
-                    if let Variable::Int(i) = *args[1].borrow_mut() {
-                        return Ok(Some(l.remove(i as usize)));
+                arg_count(args, 2, position)?;
+                let index = expect_int(&args[1], position)?;
+                match *args[0].borrow_mut() {
+                    Variable::List(ref mut l) => {
+                        let i = usize::try_from(index).ok().filter(|&i| i < l.len()).ok_or(InterpError(position, IndexOutOfRange(index, l.len())))?;
+                        l.remove(i)
                     }
-                    return Err(InterpError(position, IncorrectType(VarType::Int, args[1].borrow().to_type())));
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
                 }
-
-                todo!()
             }
-            "set" => {
-                if args.len() != 3 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::List(ref mut l) = *args[0].borrow_mut() {
-                    if let Variable::Int(i) = *args[1].borrow() {
-                        l[i as usize] = deep_copy(&args[2]);
-                        return Ok(Some(Variable::None.into()));
-                    } else {return Err(InterpError(position, IncorrectType(VarType::List, args[1].borrow().to_type())));}
-                }
-
-                if let Variable::String(ref mut l) = *args[0].borrow_mut() {
-                    if let Variable::Int(i) = *args[1].borrow() {
-                        if let Variable::Char(c) = *args[2].borrow() {
-                            l.replace_range((i as usize)..(i as usize + 1), &c.to_string());
-                            return Ok(Some(Variable::None.into()));
-                        } else {return Err(InterpError(position, IncorrectType(VarType::Char, args[2].borrow().to_type())));}
-                    } else {return Err(InterpError(position, IncorrectType(VarType::List, args[1].borrow().to_type())));}
+            // `set(xs)` with one argument builds a Set instead
+            "set" if args.len() == 3 => {
+                let index = expect_int(&args[1], position)?;
+                // a copy, so the value can be read while the List or String is being changed
+                let value = share(&args[2]);
+                match *args[0].borrow_mut() {
+                    Variable::List(ref mut l) => {
+                        let i = usize::try_from(index).ok().filter(|&i| i < l.len()).ok_or(InterpError(position, IndexOutOfRange(index, l.len())))?;
+                        l[i] = value;
+                    }
+                    Variable::String(ref mut s) => {
+                        let Variable::Char(c) = *value.borrow() else {
+                            return Err(InterpError(position, IncorrectType(VarType::Char, value.borrow().to_type())));
+                        };
+                        match usize::try_from(index).ok().and_then(|i| s.with_char(i, c)) {
+                            Some(new_string) => *s = new_string,
+                            None => return Err(InterpError(position, IndexOutOfRange(index, s.len()))),
+                        }
+                    }
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
                 }
-
-                todo!()
+                Variable::None.into()
             }
             "type" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 Variable::Type(args[0].borrow().to_type()).into()
             }
             "int" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 if let Variable::String(s) = &*args[0].borrow() {
-                    return Ok(Some(Variable::Int(s.parse().unwrap()).into()));
+                    if let Ok(i) = s.parse() {return Ok(Some(Variable::Int(i).into()));}
+                    if let Ok(i) = s.parse::<BigInt>() {return Ok(Some(Variable::BigInt(i).into()));}
+                    return Ok(Some(Variable::None.into()));
                 }
                 if let Variable::Char(c) = &*args[0].borrow() {
                     return Ok(Some(Variable::Int(c.to_string().parse().unwrap()).into()));
                 }
                 if let Variable::Float(c) = &*args[0].borrow() {
-                    return Ok(Some(Variable::Int(*c as i64).into()));
+                    return Ok(Some(match BigInt::from_f64(*c) {
+                        Some(i) => Variable::from(i).into(),
+                        None => Variable::None.into(),
+                    }));
                 }
                 Variable::None.into()
             }
             "float" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 if let Variable::Int(s) = &*args[0].borrow() {
                     return Ok(Some(Variable::Float(*s as f64).into()));
                 }
+                if let Variable::BigInt(s) = &*args[0].borrow() {
+                    return Ok(Some(Variable::Float(s.to_f64()).into()));
+                }
                 Variable::None.into()
             }
             "str" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::Int(i) = &*args[0].borrow() {
-                    return Ok(Some(Variable::String(i.to_string()).into()));
-                }
-                Variable::None.into()
+                arg_count(args, 1, position)?;
+                Variable::String(args[0].borrow().to_string().into()).into()
             }
             "len" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
+                arg_count(args, 1, position)?;
                 if let Variable::List(l) = &*args[0].borrow() {
                     return Ok(Some(Variable::Int(l.len() as i64).into()))
                 }
                 if let Variable::String(l) = &*args[0].borrow() {
                     return Ok(Some(Variable::Int(l.len() as i64).into()))
                 }
-                todo!()
-            }
-            "range" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
+                if let Variable::Tuple(t) = &*args[0].borrow() {
+                    return Ok(Some(Variable::Int(t.len() as i64).into()))
                 }
-                if let Variable::Int(l) = &*args[0].borrow() {
-                    return Ok(Some(
-                        Variable::List(
-                            (0..*l)
-                            .into_iter()
-                            .map(|x| Variable::Int(x).into())
-                            .collect()
-                        ).into()))
+                if let Variable::Hash(h) = &*args[0].borrow() {
+                    return Ok(Some(Variable::Int(h.len() as i64).into()))
                 }
-                todo!()
-            }
-            "contains" => {
-                if args.len() != 2 {
-                    return Err(InterpError(position, IncorrectArgs));
+                if let Variable::Set(s) = &*args[0].borrow() {
+                    return Ok(Some(Variable::Int(s.len() as i64).into()))
                 }
-                if let Variable::List(ref mut l) = *args[0].borrow_mut() {
-                    Variable::Bool(l.contains(&args[1])).into()
-                } else {todo!()}
-            }
-            "tcp_bind" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::String(ref s) = *args[0].borrow() {
-                    self.tcp_listener = Some(TcpListener::bind(s).unwrap());
-                } else {todo!()}
-                Variable::None.into()
+                return Err(InterpError(position, IncorrectType(VarType::List, args[0].borrow().to_type())));
             }
-            "tcp_unbind" => {
-                if args.len() != 0 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                self.tcp_listener = None;
-                Variable::None.into()
-            }
-            "tcp_listen" => {
-                if args.len() != 0 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                let mut request = String::new();
-                let (incoming, _addr) = self.tcp_listener.as_mut().unwrap().accept().unwrap();
-                let mut reader = BufReader::new(&incoming);
-                reader.read_line(&mut request).unwrap();
-                self.tcp_stream = Some(incoming);
-                Variable::String(request).into()
+            "range" => {
+                arg_count(args, 1, position)?;
+                Variable::List((0..expect_int(&args[0], position)?).map(|x| Variable::Int(x).into()).collect()).into()
             }
-            "tcp_write" => {
-                if args.len() != 1 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::String(ref s) = *args[0].borrow() {
-                    self.tcp_stream.as_mut().unwrap().write_all(s.as_bytes()).unwrap();
-                    self.tcp_stream = None;
+            "contains" => {
+                arg_count(args, 2, position)?;
+                match *args[0].borrow() {
+                    Variable::List(ref l) | Variable::Tuple(ref l) => Variable::Bool(l.contains(&args[1])).into(),
+                    // a Hash contains its keys
+                    Variable::Hash(ref h) => Variable::Bool(HashKey::from_variable(&args[1].borrow()).is_some_and(|k| h.contains_key(&k))).into(),
+                    Variable::Set(ref s) => Variable::Bool(HashKey::from_variable(&args[1].borrow()).is_some_and(|k| s.contains(&k))).into(),
+                    ref other => return Err(InterpError(position, IncorrectType(VarType::List, other.to_type()))),
                 }
-                Variable::None.into()
             }
             "split" => {
-                if args.len() != 2 {
-                    return Err(InterpError(position, IncorrectArgs));
-                }
-                if let Variable::String(ref s) = *args[0].borrow() {
-                    if let Variable::String(ref d) = *args[1].borrow() {
-                        return Ok(Some(Variable::List(s.split(d).map(|a| Variable::String(a.to_owned()).into()).collect()).into()));
-                    }
+                arg_count(args, 2, position)?;
+                let (s, d) = (expect_string(&args[0], position)?, expect_string(&args[1], position)?);
+                Variable::List(s.split(d.as_str()).map(|a| Variable::String(a.to_owned().into()).into()).collect()).into()
+            }
+            _ => {
+                // built-ins that live in their own modules
+                for module in [Self::network_function, Self::http_function, Self::json_function, Self::format_function, Self::string_function, Self::math_function, Self::random_function, Self::time_function, Self::assert_function, Self::collection_function, Self::enum_function] {
+                    if let Some(value) = module(self, function_name, args, position)? {return Ok(Some(value));}
                 }
-                todo!()
+                return Ok(None);
             }
-            _ => return Ok(None),
         }))
     }
+    /// The variable a name refers to, `None` for a constant since it cannot be assigned to
+    fn binding(&self, name: &str, local_scope: &VariableScope) -> Option<Binding> {
+        match local_scope.get(name) {
+            Some(binding) => Some(Rc::clone(binding)),
+            None if self.constants[self.file].contains(name) => None,
+            None => self.global_var_scope[self.file].get(name).map(Rc::clone),
+        }
+    }
+    /// Calls a function from the code, arguments that are just a variable name
+    /// can be passed to `ref` parameters
+    fn call_function(&mut self, function: &Function, local_scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+        let args = self.variable_from_asts(&function.args, local_scope, position)?;
+        let bindings: Vec<Option<Binding>> = function.args.iter().map(|arg| match arg {
+            ASTValue::Variable(name) => self.binding(name, local_scope),
+            _ => None,
+        }).collect();
+        self.run_function_with(&function.name, &args, &bindings, position)
+    }
     pub fn run_function(&mut self, function_name: &str, args: &Vec<VarRef>, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+        self.run_function_with(function_name, args, &[], position)
+    }
+    /// `bindings` holds the caller's variable for each argument that has one
+    fn run_function_with(&mut self, function_name: &str, args: &Vec<VarRef>, bindings: &[Option<Binding>], position: (usize, u64, u64)) -> InterpResult<VarRef> {
         if let Some(value) = self.built_in_funtion(function_name, args, position)? {return Ok(value);}
         let function = self.get_function(function_name, position)?;
         let mut function_scope = VariableScope::new();
         if function.args.len() != args.len() {return Err(InterpError(function.position, IncorrectArgs));}
-        for arg in 0..args.len() {
-            function_scope.insert(function.args[arg].clone(), Rc::clone(&args[arg]));
+        for (i, (param, arg)) in function.args.iter().zip(args).enumerate() {
+            check_annotation(arg, &param.annotation, || format!("parameter `{}` of `{function_name}`", param.name), position)?;
+            // a `ref` parameter is the caller's variable, assigning to it changes what the caller sees
+            let binding = match (param.by_ref, bindings.get(i)) {
+                (false, _) => new_binding(arg),
+                (true, Some(Some(binding))) => Rc::clone(binding),
+                (true, _) => return Err(InterpError(position, RefNotVariable(param.name.clone()))),
+            };
+            function_scope.insert(param.name.clone(), binding);
         }
         self.ret = false;
         self.brk = false;
         self.con = false;
+        let caller_file = std::mem::replace(&mut self.file, function.position.0);
         let return_value = self.run_ast_tree(&function.body, &function_scope);
+        self.file = caller_file;
         self.ret = false;
         self.brk = false;
         self.con = false;
-        return_value
+        let return_value = return_value?;
+        check_annotation(&return_value, &function.return_type, || format!("the return value of `{function_name}`"), function.position)?;
+        Ok(return_value)
     }
-    fn run_ast_tree(&mut self, body: &Vec<ASTree>, scope: &HashMap<String, VarRef>) -> InterpResult<VarRef> {
+    fn run_ast_tree(&mut self, body: &Vec<ASTree>, scope: &VariableScope) -> InterpResult<VarRef> {
         let mut current_scope = clone_scope(scope);
         let mut condition_failed = false;
         for ast in body {
+            if gc::collection_due() {
+                gc::collect_cycles();
+            }
             let position = ast.0;
             match &ast.1 {
-                ASTreeType::Let { variable, value } => {
+                ASTreeType::Let { pattern, annotation, value } => {
                     // println!("ASTreeType::Let");
-                    current_scope.insert(variable.to_owned(), self.variable_from_ast(&value, &current_scope, position)?);
+                    let value = self.variable_from_ast(value, &current_scope, position)?;
+                    check_annotation(&value, annotation, || match pattern {
+                        Pattern::Name(name) => format!("variable `{name}`"),
+                        _ => "the value being destructured".to_owned(),
+                    }, position)?;
+                    patterns::bind_pattern(pattern, value, &mut current_scope, position)?;
                 },
-                ASTreeType::Assign { variable, indexes, value } => {
+                ASTreeType::Assign { variable, annotation, indexes, value } => {
                     // println!("ASTreeType::Assign");
                     // value to be put into the variable
-                    let value = self.variable_from_ast(&value, &current_scope, position)?;
+                    let value = self.variable_from_ast(value, &current_scope, position)?;
+                    // changing an item does not change the type of the variable
+                    if indexes.is_empty() {
+                        check_annotation(&value, annotation, || format!("variable `{variable}`"), position)?;
+                    }
                     
-                    match current_scope.get(&variable.to_owned()) {
-                        Some(x) => { // original varialbe
-                            let mut changing_var = Rc::clone(x);
-                            for i in indexes {
-                                let indecie = self.variable_from_ast(i, &current_scope, position)?;
-
-                                let var_type = changing_var.borrow().to_type();
-                                if let VarType::Hash = var_type { // special rules if it's a hashmap
-                                    let new_var;
-                                    {
-                                        let mut hash = changing_var.borrow_mut();
-                                        if let Variable::Hash(h) = hash.deref_mut() {
-                                            if let Variable::String(ref s) = indecie.borrow().clone() {
-                                                if let Some(v) = h.get(s) {
-                                                    new_var = Rc::clone(v);
-                                                } else {
-                                                    new_var = Variable::None.into();
-                                                    h.insert(s.to_owned(), Rc::clone(&new_var));
-                                                }
-                                            } else {return Err(InterpError(position, NoOperation(value.borrow().to_type(), indecie.borrow().to_type(), Op::Indexing)));}
-                                        } else {unreachable!()}
-                                    }
-                                    changing_var = new_var;
-                                    break;
-                                }
-
-                                if let Some(new_var) = operations::variable_operation(changing_var, Rc::clone(&indecie), Op::Indexing) {
-                                    changing_var = Rc::clone(&new_var);
-                                } else {
-                                    return Err(InterpError(position, NoOperation(value.borrow().to_type(), indecie.borrow().to_type(), Op::Indexing)));
-                                }
-                            }
-                            *changing_var.borrow_mut() = value.borrow().clone();
+                    // a global if there is no local variable with that name
+                    let binding = match current_scope.get(variable) {
+                        Some(x) => Rc::clone(x),
+                        None if indexes.is_empty() && self.constants[self.file].contains(variable) => {
+                            return Err(InterpError(position, AssignToConstant(variable.to_owned())));
+                        },
+                        None => match self.global_var_scope[self.file].get(variable) {
+                            Some(x) => Rc::clone(x),
+                            None => return Err(InterpError(position, VarNotFound(variable.to_owned()))),
+                        },
+                    };
+                    // assigning to the name points it at the new value, anything else holding the old value keeps it
+                    let Some((last, indexes)) = indexes.split_last() else {
+                        *binding.borrow_mut() = share(&value);
+                        continue;
+                    };
+                    let mut changing_var = Rc::clone(&binding.borrow());
+                    for i in indexes {
+                        let Index::Single(i) = i else {
+                            return Err(InterpError(position, InvalidSlice("only the last index of an assignment can be a slice".to_owned())));
+                        };
+                        let indecie = self.variable_from_ast(i, &current_scope, position)?;
+                        // the items of a tuple cannot be changed
+                        let var_type = changing_var.borrow().to_type();
+                        if let VarType::Tuple = var_type {
+                            return Err(InterpError(position, Immutable(var_type)));
+                        }
+                        changing_var = operations::indexing(&changing_var, &indecie).map_err(|e| InterpError(position, e))?;
+                    }
+                    match last {
+                        Index::Single(i) => {
+                            let indecie = self.variable_from_ast(i, &current_scope, position)?;
+                            operations::assign_index(&changing_var, &indecie, share(&value)).map_err(|e| InterpError(position, e))?;
+                        },
+                        Index::Slice(slice) => {
+                            let (start, end, step) = self.slice_bounds(slice, &current_scope, position)?;
+                            operations::assign_slice(&changing_var, start, end, step, &value).map_err(|e| InterpError(position, e))?;
                         },
-                        None => return Err(InterpError(position, VarNotFound(variable.to_owned()))),
                     }
                 },
-                ASTreeType::Function(Function { name, args }) => {
+                ASTreeType::Function(function) => {
                     // println!("ASTreeType::Function");
-                    let args = &self.variable_from_asts(&args[..], &current_scope, position)?;
-                    let _ret = self.run_function(name, args, position)?;
+                    let _ret = self.call_function(function, &current_scope, position)?;
                     self.ret = false;
                     self.brk = false;
                     self.con = false;
@@ -405,8 +532,7 @@ impl CodeState {
                     if self.variable_from_ast(condition, &current_scope, position)?.borrow().to_bool() {
                         condition_failed = false;
                         let ret_value = self.run_ast_tree(body, &current_scope)?;
-                        if self.ret {return Ok(ret_value);}
-                        if self.con {return Ok(ret_value);}
+                        if self.ret || self.brk || self.con {return Ok(ret_value);}
                     } else {condition_failed = true}
                 },
                 ASTreeType::ElseIf { condition, body } => {
@@ -414,8 +540,7 @@ impl CodeState {
                     if condition_failed && self.variable_from_ast(condition, &current_scope, position)?.borrow().to_bool() {
                         condition_failed = false;
                         let ret_value = self.run_ast_tree(body, &current_scope)?;
-                        if self.ret {return Ok(ret_value);}
-                        if self.con {return Ok(ret_value);}
+                        if self.ret || self.brk || self.con {return Ok(ret_value);}
                     }
                 },
                 ASTreeType::Else { body } => {
@@ -423,8 +548,7 @@ impl CodeState {
                     if condition_failed {
                         condition_failed = false;
                         let ret_value = self.run_ast_tree(body, &current_scope)?;
-                        if self.ret {return Ok(ret_value);}
-                        if self.con {return Ok(ret_value);}
+                        if self.ret || self.brk || self.con {return Ok(ret_value);}
                     }
                 },
                 ASTreeType::While { condition, body } => {
@@ -450,22 +574,41 @@ impl CodeState {
                     self.ret = true;
                     return self.variable_from_ast(value, &current_scope, position);
                 },
-                ASTreeType::For(var, ast_list, body) => {
+                ASTreeType::For(pattern, ast_list, body) => {
                     // println!("ASTreeType::For");
                     let list_ref = self.variable_from_ast(ast_list, &current_scope, position)?;
-                    if let Variable::List(list) = &*list_ref.borrow() {
-                        let mut loop_scope = current_scope.clone();
-                        for i in list {
-                            loop_scope.insert(var.to_owned(), Rc::clone(i));
-                            let ret_value = self.run_ast_tree(body, &loop_scope)?;
-                            if self.ret {return Ok(ret_value);}
-                            if self.brk {self.brk = false;break;}
-                            if self.con {self.con = false;}
-                        }
-                    } else {
-                        return Err(InterpError(position, IncorrectType(VarType::List, list_ref.borrow().to_type())));
+                    let items: Vec<VarRef> = match &*list_ref.borrow() {
+                        Variable::List(list) => list.iter().map(Rc::clone).collect(),
+                        Variable::Tuple(items) => items.iter().map(Rc::clone).collect(),
+                        // a Hash goes through its keys and a Set its items, in the order they were inserted
+                        Variable::Hash(hash) => hash.keys().map(|k| k.to_variable().into()).collect(),
+                        Variable::Set(set) => set.iter().map(|k| k.to_variable().into()).collect(),
+                        x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
                     };
+                    let mut loop_scope = current_scope.clone();
+                    for i in items {
+                        patterns::bind_pattern(pattern, i, &mut loop_scope, position)?;
+                        let ret_value = self.run_ast_tree(body, &loop_scope)?;
+                        if self.ret {return Ok(ret_value);}
+                        if self.brk {self.brk = false;break;}
+                        if self.con {self.con = false;}
+                    }
+                },
+                ASTreeType::Match(value, arms) => {
+                    // println!("ASTreeType::Match");
+                    let value = self.variable_from_ast(value, &current_scope, position)?;
+                    match self.find_arm(&value, arms, &current_scope, position)? {
+                        (ArmBody::Block(body), arm_scope) => {
+                            let ret_value = self.run_ast_tree(body, &arm_scope)?;
+                            if self.ret || self.brk || self.con {return Ok(ret_value);}
+                        },
+                        (ArmBody::Value(result), arm_scope) => {
+                            self.variable_from_ast(result, &arm_scope, position)?;
+                        },
+                    }
                 },
+                // the parser only allows `const` at the top level
+                ASTreeType::Const { .. } => unreachable!(),
                 ASTreeType::Break => {
                     // println!("ASTreeType::Break");
                     self.brk = true;
@@ -0,0 +1,233 @@
+use std::{io::{self, BufRead, BufReader, Read, Write}, net::{SocketAddr, TcpListener, TcpStream, UdpSocket}, time::Duration};
+
+use super::{args::{arg_count, expect_bool, expect_int, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, variables::BeetleHash, CodeState, VarRef, VarType, Variable};
+
+/// A socket owned by the interpreter, referred to from Beetle code by its handle id
+pub enum Socket {
+    Listener(TcpListener),
+    Stream(BufReader<TcpStream>),
+    Udp(UdpSocket),
+}
+
+/// Returns true if the error means a read ran out of time rather than failed
+fn timed_out(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// Parses an `ip:port` address without doing any DNS lookups
+fn parse_address(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<SocketAddr> {
+    let address = expect_string(arg, position)?;
+    address.parse().map_err(|_| InterpError(position, Io(format!("invalid address `{address}`, expected `ip:port`"))))
+}
+
+fn parse_timeout(arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<Option<Duration>> {
+    match *arg.borrow() {
+        Variable::None => Ok(None),
+        Variable::Int(ms) if ms > 0 => Ok(Some(Duration::from_millis(ms as u64))),
+        Variable::Int(_) => Err(InterpError(position, Io("timeout must be greater than zero".to_owned()))),
+        ref x => Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+    }
+}
+
+pub(super) fn io_error(position: (usize, u64, u64), error: io::Error) -> InterpError {
+    InterpError(position, Io(error.to_string()))
+}
+
+impl CodeState {
+    /// Gives the socket a new id. Ids are never reused, so a handle kept after `close` cannot reach a later socket
+    pub(super) fn add_socket(&mut self, socket: Socket) -> VarRef {
+        let id = self.next_socket;
+        self.next_socket += 1;
+        self.sockets.insert(id, socket);
+        Variable::Socket(id).into()
+    }
+    pub(super) fn get_socket(&mut self, arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<&mut Socket> {
+        let id = match *arg.borrow() {
+            Variable::Socket(id) => id,
+            ref x => return Err(InterpError(position, IncorrectType(VarType::Socket, x.to_type()))),
+        };
+        self.sockets.get_mut(&id).ok_or(InterpError(position, InvalidSocket(id)))
+    }
+    pub(super) fn get_stream(&mut self, arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<&mut BufReader<TcpStream>> {
+        match self.get_socket(arg, position)? {
+            Socket::Stream(stream) => Ok(stream),
+            _ => Err(InterpError(position, Io("expected a connected TCP stream".to_owned()))),
+        }
+    }
+    fn get_udp(&mut self, arg: &VarRef, position: (usize, u64, u64)) -> InterpResult<&mut UdpSocket> {
+        match self.get_socket(arg, position)? {
+            Socket::Udp(socket) => Ok(socket),
+            _ => Err(InterpError(position, Io("expected a UDP socket".to_owned()))),
+        }
+    }
+    pub(super) fn network_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "tcp_bind" => {
+                arg_count(args, 1, position)?;
+                let listener = TcpListener::bind(expect_string(&args[0], position)?.as_str()).map_err(|e| io_error(position, e))?;
+                self.add_socket(Socket::Listener(listener))
+            }
+            "tcp_accept" => {
+                arg_count(args, 1, position)?;
+                let accepted = match self.get_socket(&args[0], position)? {
+                    Socket::Listener(listener) => listener.accept(),
+                    _ => return Err(InterpError(position, Io("expected a TCP listener".to_owned()))),
+                };
+                let (stream, _addr) = match accepted {
+                    Ok(x) => x,
+                    // a non-blocking listener without a waiting connection
+                    Err(e) if timed_out(&e) => return Ok(Some(Variable::None.into())),
+                    Err(e) => return Err(io_error(position, e)),
+                };
+                // accepted streams must block even if the listener does not
+                stream.set_nonblocking(false).map_err(|e| io_error(position, e))?;
+                self.add_socket(Socket::Stream(BufReader::new(stream)))
+            }
+            "tcp_connect" => {
+                arg_count(args, 1, position)?;
+                let stream = TcpStream::connect(expect_string(&args[0], position)?.as_str()).map_err(|e| io_error(position, e))?;
+                self.add_socket(Socket::Stream(BufReader::new(stream)))
+            }
+            "read_line" => {
+                arg_count(args, 1, position)?;
+                let stream = self.get_stream(&args[0], position)?;
+                let mut line = String::new();
+                match stream.read_line(&mut line) {
+                    Ok(0) => Variable::None.into(), // end of stream
+                    Ok(_) => {
+                        if line.ends_with('\n') {line.pop();}
+                        if line.ends_with('\r') {line.pop();}
+                        Variable::String(line.into()).into()
+                    }
+                    Err(e) if timed_out(&e) => Variable::None.into(),
+                    Err(e) => return Err(io_error(position, e)),
+                }
+            }
+            "read_bytes" => {
+                arg_count(args, 2, position)?;
+                let count = expect_int(&args[1], position)?.max(0) as u64;
+                let stream = self.get_stream(&args[0], position)?;
+                let mut buffer = Vec::new();
+                match stream.take(count).read_to_end(&mut buffer) {
+                    Ok(_) => (),
+                    Err(e) if timed_out(&e) => if buffer.is_empty() {return Ok(Some(Variable::None.into()));},
+                    Err(e) => return Err(io_error(position, e)),
+                }
+                Variable::String(String::from_utf8_lossy(&buffer).into_owned().into()).into()
+            }
+            "read_all" => {
+                arg_count(args, 1, position)?;
+                let stream = self.get_stream(&args[0], position)?;
+                let mut buffer = Vec::new();
+                match stream.read_to_end(&mut buffer) {
+                    Ok(_) => (),
+                    Err(e) if timed_out(&e) => if buffer.is_empty() {return Ok(Some(Variable::None.into()));},
+                    Err(e) => return Err(io_error(position, e)),
+                }
+                Variable::String(String::from_utf8_lossy(&buffer).into_owned().into()).into()
+            }
+            "write" => {
+                arg_count(args, 2, position)?;
+                let data = expect_string(&args[1], position)?;
+                let stream = self.get_stream(&args[0], position)?.get_mut();
+                stream.write_all(data.as_bytes()).map_err(|e| io_error(position, e))?;
+                stream.flush().map_err(|e| io_error(position, e))?;
+                Variable::None.into()
+            }
+            "close" => {
+                arg_count(args, 1, position)?;
+                self.get_socket(&args[0], position)?;
+                if let Variable::Socket(id) = *args[0].borrow() {
+                    // dropping the socket closes it
+                    self.sockets.remove(&id);
+                }
+                Variable::None.into()
+            }
+            "set_timeout" => {
+                arg_count(args, 2, position)?;
+                let timeout = parse_timeout(&args[1], position)?;
+                match self.get_socket(&args[0], position)? {
+                    Socket::Stream(stream) => {
+                        stream.get_ref().set_read_timeout(timeout).and(stream.get_ref().set_write_timeout(timeout))
+                    }
+                    Socket::Udp(socket) => socket.set_read_timeout(timeout).and(socket.set_write_timeout(timeout)),
+                    Socket::Listener(_) => return Err(InterpError(position, Io("timeouts are not supported on listeners, use set_nonblocking".to_owned()))),
+                }.map_err(|e| io_error(position, e))?;
+                Variable::None.into()
+            }
+            "set_nonblocking" => {
+                arg_count(args, 2, position)?;
+                let nonblocking = expect_bool(&args[1], position)?;
+                match self.get_socket(&args[0], position)? {
+                    Socket::Listener(listener) => listener.set_nonblocking(nonblocking),
+                    Socket::Stream(stream) => stream.get_ref().set_nonblocking(nonblocking),
+                    Socket::Udp(socket) => socket.set_nonblocking(nonblocking),
+                }.map_err(|e| io_error(position, e))?;
+                Variable::None.into()
+            }
+            "peer_addr" => {
+                arg_count(args, 1, position)?;
+                let address = match self.get_socket(&args[0], position)? {
+                    Socket::Stream(stream) => stream.get_ref().peer_addr(),
+                    Socket::Udp(socket) => socket.peer_addr(),
+                    Socket::Listener(_) => return Err(InterpError(position, Io("listeners do not have a peer".to_owned()))),
+                }.map_err(|e| io_error(position, e))?;
+                Variable::String(address.to_string().into()).into()
+            }
+            "local_addr" => {
+                arg_count(args, 1, position)?;
+                let address = match self.get_socket(&args[0], position)? {
+                    Socket::Listener(listener) => listener.local_addr(),
+                    Socket::Stream(stream) => stream.get_ref().local_addr(),
+                    Socket::Udp(socket) => socket.local_addr(),
+                }.map_err(|e| io_error(position, e))?;
+                Variable::String(address.to_string().into()).into()
+            }
+            "udp_bind" => {
+                arg_count(args, 1, position)?;
+                let socket = UdpSocket::bind(parse_address(&args[0], position)?).map_err(|e| io_error(position, e))?;
+                self.add_socket(Socket::Udp(socket))
+            }
+            "udp_send_to" => {
+                arg_count(args, 3, position)?;
+                let data = expect_string(&args[1], position)?;
+                let address = parse_address(&args[2], position)?;
+                let sent = self.get_udp(&args[0], position)?.send_to(data.as_bytes(), address).map_err(|e| io_error(position, e))?;
+                Variable::Int(sent as i64).into()
+            }
+            "udp_recv_from" => {
+                arg_count(args, 1, position)?;
+                let mut buffer = [0; 65536];
+                match self.get_udp(&args[0], position)?.recv_from(&mut buffer) {
+                    Ok((size, address)) => Variable::Tuple(vec![
+                        Variable::String(String::from_utf8_lossy(&buffer[..size]).into_owned().into()).into(),
+                        Variable::String(address.to_string().into()).into(),
+                    ]).into(),
+                    Err(e) if timed_out(&e) => Variable::None.into(),
+                    Err(e) => return Err(io_error(position, e)),
+                }
+            }
+            "parse_addr" => {
+                arg_count(args, 1, position)?;
+                let Ok(address) = expect_string(&args[0], position)?.parse::<SocketAddr>() else {return Ok(Some(Variable::None.into()))};
+                let mut hash = BeetleHash::new();
+                hash.insert("ip".into(), Variable::String(address.ip().to_string().into()).into());
+                hash.insert("port".into(), Variable::Int(address.port() as i64).into());
+                hash.insert("version".into(), Variable::Int(if address.is_ipv4() {4} else {6}).into());
+                Variable::Hash(hash).into()
+            }
+            "format_addr" => {
+                arg_count(args, 2, position)?;
+                let ip = expect_string(&args[0], position)?;
+                let ip: std::net::IpAddr = ip.parse().map_err(|_| InterpError(position, Io(format!("invalid ip address `{ip}`"))))?;
+                let port = match *args[1].borrow() {
+                    Variable::Int(p) if (0..=65535).contains(&p) => p as u16,
+                    Variable::Int(p) => return Err(InterpError(position, Io(format!("port {p} is out of range")))),
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Int, x.to_type()))),
+                };
+                Variable::String(SocketAddr::new(ip, port).to_string().into()).into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
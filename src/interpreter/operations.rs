@@ -1,13 +1,12 @@
 use std::rc::Rc;
 
-use crate::ast::Op;
+use crate::{ast::Op, bigint::BigInt};
 
-use super::{VarRef, VarType, Variable};
+use super::{interpreter_error::InterpErrorType::{self, *}, variables::{share, HashKey}, VarRef, VarType, Variable};
 
-pub fn variable_operation(var1: VarRef, var2: VarRef, op: Op) -> Option<VarRef> {
+pub fn variable_operation(var1: VarRef, var2: VarRef, op: Op) -> Result<VarRef, InterpErrorType> {
     let types = (var1.borrow().to_type(), var2.borrow().to_type());
-    if op == Op::Indexing {return indexing(types, var1, var2);}
-    match types {
+    let result = match types {
 
         (VarType::Bool, VarType::Bool) => {
             if let (Variable::Bool(x), Variable::Bool(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
@@ -16,20 +15,25 @@ pub fn variable_operation(var1: VarRef, var2: VarRef, op: Op) -> Option<VarRef>
         },
         
         (VarType::Int, VarType::Int) => {
-            if let (Variable::Int(x), Variable::Int(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                int_operation(x, y, op)
-            } else {unreachable!()}
+            let dividing = matches!(op, Op::Division | Op::Modulus);
+            match (&*var1.borrow(), &*var2.borrow()) {
+                // there is no answer, not even as a big integer
+                (_, Variable::Int(0)) if dividing => return Err(DivisionByZero),
+                (_, Variable::BigInt(y)) if dividing && y.is_zero() => return Err(DivisionByZero),
+                (Variable::Int(x), Variable::Int(y)) => int_operation(*x, *y, op),
+                (x, y) => big_int_operation(&to_big_int(x), &to_big_int(y), op),
+            }
         },
         
         (VarType::Int, VarType::Float) => {
-            if let (Variable::Int(x), Variable::Float(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                float_operation(x as f64, y, op)
+            if let Variable::Float(y) = var2.borrow().clone() {
+                float_operation(int_to_float(&var1.borrow()), y, op)
             } else {unreachable!()}
         },
 
         (VarType::Float, VarType::Int) => {
-            if let (Variable::Float(x), Variable::Int(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                float_operation(x, y as f64, op)
+            if let Variable::Float(x) = var1.borrow().clone() {
+                float_operation(x, int_to_float(&var2.borrow()), op)
             } else {unreachable!()}
         },
 
@@ -57,46 +61,258 @@ pub fn variable_operation(var1: VarRef, var2: VarRef, op: Op) -> Option<VarRef>
             } else {unreachable!()}
         },
         
+        // variants are equal when they are the same variant holding equal fields
+        (VarType::Enum(_), VarType::Enum(_)) => {
+            match op {
+                Op::Equality => Some(Variable::Bool(*var1.borrow() == *var2.borrow()).into()),
+                Op::NotEquality => Some(Variable::Bool(*var1.borrow() != *var2.borrow()).into()),
+                _ => None,
+            }
+        },
+
+        // anything can be compared against none, e.g. the result of `read_line` at the end of a stream
+        (VarType::None, _) | (_, VarType::None) => {
+            match op {
+                Op::Equality => Some(Variable::Bool(types.0 == types.1).into()),
+                Op::NotEquality => Some(Variable::Bool(types.0 != types.1).into()),
+                _ => None,
+            }
+        },
+        
         _ => None
+    };
+    result.ok_or(NoOperation(types.0, types.1, op))
+}
+
+/// Turns an index that may count back from the end into one from the start
+fn list_index(index: i64, length: usize) -> Result<usize, InterpErrorType> {
+    let from_start = if index < 0 {index + length as i64} else {index};
+    if from_start < 0 || from_start >= length as i64 {
+        return Err(IndexOutOfRange(index, length));
     }
+    Ok(from_start as usize)
 }
 
-fn indexing(types: (VarType, VarType), var1: VarRef, var2: VarRef) -> Option<VarRef> {
-    Some(match types {
-        (VarType::List, VarType::Int) => {
-            if let (Variable::List(x), Variable::Int(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                Rc::clone(&x[y as usize])
-            } else {unreachable!()}
+pub fn indexing(var1: &VarRef, var2: &VarRef) -> Result<VarRef, InterpErrorType> {
+    Ok(match (&*var1.borrow(), &*var2.borrow()) {
+        (Variable::List(x), Variable::Int(y)) => Rc::clone(&x[list_index(*y, x.len())?]),
+        (Variable::Tuple(x), Variable::Int(y)) => share(&x[list_index(*y, x.len())?]),
+        (Variable::String(x), Variable::Int(y)) => Variable::Char(x.char_at(list_index(*y, x.len())?).unwrap()).into(),
+        (Variable::Hash(x), y) => {
+            if let Some(x) = x.get(&hash_key(y)?) {Rc::clone(x)}
+            else {Variable::None.into()}
         },
-        (VarType::String, VarType::Int) => {
-            if let (Variable::String(x), Variable::Int(y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                Variable::Char(x.chars().nth(y as usize).unwrap()).into()
-            } else {unreachable!()}
+        (x, y) => return Err(NoOperation(x.to_type(), y.to_type(), Op::Indexing)),
+    })
+}
+
+pub fn hash_key(value: &Variable) -> Result<HashKey, InterpErrorType> {
+    HashKey::from_variable(value).ok_or(Unhashable(value.to_type()))
+}
+
+/// The indexes picked out by a slice, following the same rules as Python.
+/// Missing bounds default to the whole sequence, negative ones count back from the end
+/// and everything is clamped so slicing never goes out of range.
+fn slice_indexes(length: usize, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Result<Vec<usize>, InterpErrorType> {
+    let length = length as i64;
+    let step = step.unwrap_or(1);
+    if step == 0 {return Err(InvalidSlice("slice step cannot be zero".to_owned()));}
+
+    let clamp = |index: i64, low: i64, high: i64| {
+        let index = if index < 0 {index + length} else {index};
+        index.clamp(low, high)
+    };
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let start = start.map_or(0, |s| clamp(s, 0, length));
+        let end = end.map_or(length, |e| clamp(e, 0, length));
+        let mut i = start;
+        while i < end {
+            indexes.push(i as usize);
+            // a huge step goes past the end in one go
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
+        }
+    } else {
+        let start = start.map_or(length - 1, |s| clamp(s, -1, length - 1));
+        let end = end.map_or(-1, |e| clamp(e, -1, length - 1));
+        let mut i = start;
+        while i > end {
+            indexes.push(i as usize);
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
+        }
+    }
+    Ok(indexes)
+}
+
+/// The range covered by a slice with the default step of one
+fn contiguous_range(length: usize, start: Option<i64>, end: Option<i64>) -> (usize, usize) {
+    let clamp = |index: i64| {
+        let index = if index < 0 {index + length as i64} else {index};
+        index.clamp(0, length as i64) as usize
+    };
+    let start = start.map_or(0, clamp);
+    let end = end.map_or(length, clamp);
+    (start, end.max(start))
+}
+
+/// Copies part of a List or String into a new one
+pub fn slice(value: &VarRef, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Result<VarRef, InterpErrorType> {
+    Ok(match &*value.borrow() {
+        Variable::List(list) => {
+            let indexes = slice_indexes(list.len(), start, end, step)?;
+            Variable::List(indexes.into_iter().map(|i| share(&list[i])).collect()).into()
         },
-        (VarType::Hash, VarType::String) => {
-            if let (Variable::Hash(x), Variable::String(ref y)) = (var1.borrow().clone(), var2.borrow().clone()) {
-                if let Some(x) = x.get(y) {return Some(Rc::clone(x));}
-                else {
-                    return Some(Variable::None.into());
-                }
-            } else {unreachable!()}
+        Variable::Tuple(tuple) => {
+            let indexes = slice_indexes(tuple.len(), start, end, step)?;
+            Variable::Tuple(indexes.into_iter().map(|i| share(&tuple[i])).collect()).into()
         },
-        _ => return None,
+        Variable::String(string) => {
+            if step.unwrap_or(1) == 1 {
+                // the common case can be copied in one go
+                let (start, end) = contiguous_range(string.len(), start, end);
+                return Ok(Variable::String(string.slice(start, end).into()).into());
+            }
+            let indexes = slice_indexes(string.len(), start, end, step)?;
+            Variable::String(indexes.into_iter().map(|i| string.char_at(i).unwrap()).collect::<String>().into()).into()
+        },
+        x => return Err(IncorrectType(VarType::List, x.to_type())),
     })
 }
 
+/// Replaces part of a List or String, `xs[1:3] = [...]`.
+/// With the default step the replacement can be a different length, otherwise it must match the slice
+pub fn assign_slice(target: &VarRef, start: Option<i64>, end: Option<i64>, step: Option<i64>, value: &VarRef) -> Result<(), InterpErrorType> {
+    let new_value = match (&*target.borrow(), &*value.borrow()) {
+        (Variable::List(list), Variable::List(replacement)) => {
+            let replacement: Vec<VarRef> = replacement.iter().map(share).collect();
+            let mut new_list = list.clone();
+            if step.unwrap_or(1) == 1 {
+                let (start, end) = contiguous_range(list.len(), start, end);
+                new_list.splice(start..end, replacement);
+            } else {
+                let indexes = slice_indexes(list.len(), start, end, step)?;
+                if indexes.len() != replacement.len() {
+                    return Err(InvalidSlice(format!("cannot assign {} items to a slice of {}", replacement.len(), indexes.len())));
+                }
+                for (i, v) in indexes.into_iter().zip(replacement) {new_list[i] = v;}
+            }
+            Variable::List(new_list)
+        },
+        (Variable::String(string), Variable::String(replacement)) => {
+            let mut chars: Vec<char> = string.chars().collect();
+            if step.unwrap_or(1) == 1 {
+                let (start, end) = contiguous_range(string.len(), start, end);
+                chars.splice(start..end, replacement.chars());
+            } else {
+                let indexes = slice_indexes(string.len(), start, end, step)?;
+                if indexes.len() != replacement.len() {
+                    return Err(InvalidSlice(format!("cannot assign {} characters to a slice of {}", replacement.len(), indexes.len())));
+                }
+                for (i, c) in indexes.into_iter().zip(replacement.chars()) {chars[i] = c;}
+            }
+            Variable::String(chars.into_iter().collect::<String>().into())
+        },
+        (Variable::Tuple(_), _) => return Err(Immutable(VarType::Tuple)),
+        (Variable::List(_), x) => return Err(IncorrectType(VarType::List, x.to_type())),
+        (Variable::String(_), x) => return Err(IncorrectType(VarType::String, x.to_type())),
+        (x, _) => return Err(IncorrectType(VarType::List, x.to_type())),
+    };
+    *target.borrow_mut() = new_value;
+    Ok(())
+}
+
+/// Puts the value at an index of a List or Hash. The old item is replaced rather than changed,
+/// so anything else holding it is left alone
+pub fn assign_index(target: &VarRef, index: &VarRef, value: VarRef) -> Result<(), InterpErrorType> {
+    // the index is read before the target is borrowed to change it, as `h[h] = x` makes them the same value
+    let key = match (&*target.borrow(), &*index.borrow()) {
+        (Variable::Hash(_), key) => Some(hash_key(key)?),
+        _ => None,
+    };
+    let index_type = index.borrow().to_type();
+    let position = if let Variable::Int(i) = *index.borrow() {Some(i)} else {None};
+    match (&mut *target.borrow_mut(), key, position) {
+        (Variable::Hash(hash), Some(key), _) => {hash.insert(key, value);},
+        (Variable::List(list), _, Some(i)) => {
+            let i = list_index(i, list.len())?;
+            list[i] = value;
+        },
+        (Variable::String(string), _, Some(i)) => {
+            let Variable::Char(c) = *value.borrow() else {return Err(IncorrectType(VarType::Char, value.borrow().to_type()))};
+            let index = list_index(i, string.len())?;
+            *string = string.with_char(index, c).unwrap();
+        },
+        (Variable::Tuple(_), ..) => return Err(Immutable(VarType::Tuple)),
+        (x, ..) => return Err(NoOperation(x.to_type(), index_type, Op::Indexing)),
+    }
+    Ok(())
+}
+
+fn to_big_int(value: &Variable) -> BigInt {
+    match value {
+        Variable::Int(i) => (*i).into(),
+        Variable::BigInt(i) => i.clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn int_to_float(value: &Variable) -> f64 {
+    match value {
+        Variable::Int(i) => *i as f64,
+        Variable::BigInt(i) => i.to_f64(),
+        _ => unreachable!(),
+    }
+}
+
 fn int_operation(x: i64, y: i64, op: Op) -> Option<VarRef> {
+    let result = match op {
+        Op::Addition => x.checked_add(y),
+        Op::Subtraction => x.checked_sub(y),
+        Op::Multiplication => x.checked_mul(y),
+        // the divisor is never zero here, so these only fail when `i64::MIN / -1` overflows
+        Op::Division => x.checked_div(y),
+        // a negative power is a fraction, so it is worked out as a Float
+        Op::Power if y < 0 => return Some(Variable::Float((x as f64).powf(y as f64)).into()),
+        Op::Power => u32::try_from(y).ok().and_then(|y| x.checked_pow(y)),
+        Op::Equality => return Some(Variable::Bool(x==y).into()),
+        Op::NotEquality => return Some(Variable::Bool(x!=y).into()),
+        Op::Indexing => return None,
+        Op::And => Some(x&y),
+        Op::Or => Some(x|y),
+        Op::Modulus => x.checked_rem(y),
+        Op::LessThan => return Some(Variable::Bool(x<y).into()),
+        Op::GreaterThan => return Some(Variable::Bool(x>y).into()),
+    };
+    match result {
+        Some(i) => Some(Variable::Int(i).into()),
+        // it overflowed, so work it out again without a limit
+        None => big_int_operation(&x.into(), &y.into(), op),
+    }
+}
+
+fn big_int_operation(x: &BigInt, y: &BigInt, op: Op) -> Option<VarRef> {
     Some(match op {
-        Op::Addition => Variable::Int(x+y).into(),
-        Op::Subtraction => Variable::Int(x-y).into(),
-        Op::Multiplication => Variable::Int(x*y).into(),
-        Op::Division => Variable::Int(x/y).into(), // TODO: y is zero
+        Op::Addition => Variable::from(x+y).into(),
+        Op::Subtraction => Variable::from(x-y).into(),
+        Op::Multiplication => Variable::from(x*y).into(),
+        Op::Division => Variable::from(x.div_rem(y)?.0).into(),
+        Op::Power => match y.to_i64().and_then(|y| u32::try_from(y).ok()) {
+            Some(y) => Variable::from(x.pow(y)).into(),
+            // the result would not fit in memory, or is a fraction
+            None => Variable::Float(x.to_f64().powf(y.to_f64())).into(),
+        },
         Op::Equality => Variable::Bool(x==y).into(),
         Op::NotEquality => Variable::Bool(x!=y).into(),
         Op::Indexing => return None,
-        Op::And => Variable::Int(x&y).into(),
-        Op::Or => Variable::Int(x|y).into(),
-        Op::Modulus => Variable::Int(x%y).into(),
+        Op::And => Variable::from(x&y).into(),
+        Op::Or => Variable::from(x|y).into(),
+        Op::Modulus => Variable::from(x.div_rem(y)?.1).into(),
         Op::LessThan => Variable::Bool(x<y).into(),
         Op::GreaterThan => Variable::Bool(x>y).into(),
     })
@@ -108,6 +324,7 @@ fn float_operation(x: f64, y: f64, op: Op) -> Option<VarRef> {
         Op::Subtraction => Variable::Float(x-y).into(),
         Op::Multiplication => Variable::Float(x*y).into(),
         Op::Division => Variable::Float(x/y).into(),
+        Op::Power => Variable::Float(x.powf(y)).into(),
         Op::Equality => Variable::Bool(x==y).into(),
         Op::NotEquality => Variable::Bool(x!=y).into(),
         Op::Indexing => return None,
@@ -124,10 +341,13 @@ fn string_operation(x: &str, y: &str, op: Op) -> Option<VarRef> {
         Op::Addition => {
             let mut new_string = x.to_owned();
             new_string.push_str(y);
-            Variable::String(new_string).into()
+            Variable::String(new_string.into()).into()
         },
         Op::Equality => Variable::Bool(x==y).into(),
         Op::NotEquality => Variable::Bool(x!=y).into(),
+        // compares character by character using their unicode code points
+        Op::LessThan => Variable::Bool(x<y).into(),
+        Op::GreaterThan => Variable::Bool(x>y).into(),
         _ => return None,
     })
 }
@@ -146,6 +366,8 @@ fn char_operation(x: char, y: char, op: Op) -> Option<VarRef> {
     Some(match op {
         Op::Equality => Variable::Bool(x==y).into(),
         Op::NotEquality => Variable::Bool(x!=y).into(),
+        Op::LessThan => Variable::Bool(x<y).into(),
+        Op::GreaterThan => Variable::Bool(x>y).into(),
         _ => return None,
     })
 }
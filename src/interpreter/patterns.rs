@@ -0,0 +1,158 @@
+use std::rc::Rc;
+
+use crate::ast::{ASTValue, ArmBody, MatchArm, Pattern, VariantArgs};
+
+use super::{interpreter_error::{InterpError, InterpResult, InterpErrorType::{self, *}}, operations::hash_key, variables::{new_binding, share}, CodeState, VarRef, VarType, Variable, VariableScope};
+
+/// Adds the names in the pattern to the scope, bound to the part of the value they line up with
+pub(super) fn bind_pattern(pattern: &Pattern, value: VarRef, scope: &mut VariableScope, position: (usize, u64, u64)) -> InterpResult<()> {
+    match_pattern(pattern, value, scope).map_err(|e| InterpError(position, e))
+}
+
+/// The value of a literal in a pattern, the parser only allows constants there
+fn literal(value: &ASTValue) -> Variable {
+    match value {
+        ASTValue::Int(i) => Variable::Int(*i),
+        ASTValue::BigInt(i) => Variable::BigInt(i.clone()),
+        ASTValue::Float(f) => Variable::Float(*f),
+        ASTValue::String(s) => Variable::String(s.as_str().into()),
+        ASTValue::Char(c) => Variable::Char(*c),
+        ASTValue::Bool(b) => Variable::Bool(*b),
+        ASTValue::None => Variable::None,
+        _ => unreachable!(),
+    }
+}
+
+/// Binds the pattern, or gives the reason it does not match.
+/// Names bound before finding a mismatch are left in the scope, except those of `|` alternatives that did not match
+fn match_pattern(pattern: &Pattern, value: VarRef, scope: &mut VariableScope) -> Result<(), InterpErrorType> {
+    match pattern {
+        Pattern::Name(name) => {scope.insert(name.to_owned(), new_binding(&value));},
+        Pattern::Wildcard => {},
+        Pattern::Value(expected) => {
+            let expected = literal(expected);
+            if *value.borrow() != expected {
+                return Err(PatternMismatch(format!("expected {}, got {}", expected.repr(), value.borrow().repr())));
+            }
+        },
+        Pattern::Type(name, inner) => {
+            let var_type = value.borrow().to_type();
+            if var_type.to_string() != *name {
+                return Err(PatternMismatch(format!("expected type {name}, got type {var_type}")));
+            }
+            match_pattern(inner, value, scope)?;
+        },
+        Pattern::Tuple(patterns) => {
+            let items: Vec<VarRef> = match &*value.borrow() {
+                Variable::Tuple(items) => items.iter().map(Rc::clone).collect(),
+                x => return Err(IncorrectType(VarType::Tuple, x.to_type())),
+            };
+            match_items(patterns, items, Variable::Tuple, scope)?;
+        },
+        Pattern::List(patterns) => {
+            let items: Vec<VarRef> = match &*value.borrow() {
+                Variable::List(items) => items.iter().map(Rc::clone).collect(),
+                x => return Err(IncorrectType(VarType::List, x.to_type())),
+            };
+            match_items(patterns, items, Variable::List, scope)?;
+        },
+        Pattern::Enum(name, variant, args) => {
+            let value = match &*value.borrow() {
+                Variable::Enum(value) if *value.name == **name && *value.variant == **variant => value.clone(),
+                Variable::Enum(value) => return Err(PatternMismatch(format!("expected {name}::{variant}, got {}::{}", value.name, value.variant))),
+                x => return Err(IncorrectType(VarType::Enum(name.as_str().into()), x.to_type())),
+            };
+            match args {
+                VariantArgs::Unit => {},
+                VariantArgs::Tuple(patterns) => {
+                    let fields = value.fields.iter().map(Rc::clone).collect();
+                    match_items(patterns, fields, Variable::Tuple, scope)?;
+                },
+                VariantArgs::Struct(patterns) => for (field, pattern) in patterns {
+                    match value.field_names.iter().position(|f| f == field) {
+                        Some(i) => match_pattern(pattern, Rc::clone(&value.fields[i]), scope)?,
+                        None => return Err(PatternMismatch(format!("{name}::{variant} has no field {field}"))),
+                    }
+                },
+            }
+        },
+        // the parser only allows `..` inside a tuple or list pattern
+        Pattern::Rest(_) => unreachable!(),
+        Pattern::Hash(entries) => {
+            for (key, pattern) in entries {
+                let item = match &*value.borrow() {
+                    Variable::Hash(hash) => hash.get(&hash_key(&literal(key))?).map(Rc::clone),
+                    x => return Err(IncorrectType(VarType::Hash, x.to_type())),
+                };
+                match item {
+                    Some(item) => match_pattern(pattern, item, scope)?,
+                    None => return Err(PatternMismatch(format!("missing key {}", literal(key).repr()))),
+                }
+            }
+        },
+        Pattern::Or(alternatives) => {
+            let mut error = None;
+            for alternative in alternatives {
+                // an alternative that fails part way through must not leave its names behind for the next one
+                let mut bound = VariableScope::new();
+                match match_pattern(alternative, Rc::clone(&value), &mut bound) {
+                    Ok(()) => {
+                        scope.extend(bound);
+                        return Ok(());
+                    },
+                    Err(e) => error = Some(e),
+                }
+            }
+            return Err(error.unwrap());
+        },
+    }
+    Ok(())
+}
+
+/// Lines the items up with the patterns, the `..rest` pattern if there is one gets whatever is left over
+fn match_items(patterns: &[Pattern], mut items: Vec<VarRef>, rest_kind: fn(Vec<VarRef>) -> Variable, scope: &mut VariableScope) -> Result<(), InterpErrorType> {
+    let Some(rest) = patterns.iter().position(|p| matches!(p, Pattern::Rest(_))) else {
+        if patterns.len() != items.len() {
+            return Err(PatternMismatch(format!("expected {} items, got {}", patterns.len(), items.len())));
+        }
+        for (pattern, item) in patterns.iter().zip(items) {
+            match_pattern(pattern, item, scope)?;
+        }
+        return Ok(());
+    };
+
+    let after = patterns.len() - rest - 1;
+    if items.len() < rest + after {
+        return Err(PatternMismatch(format!("expected at least {} items, got {}", rest + after, items.len())));
+    }
+    let tail = items.split_off(items.len() - after);
+    let middle = items.split_off(rest);
+    for (pattern, item) in patterns[..rest].iter().zip(items) {
+        match_pattern(pattern, item, scope)?;
+    }
+    if let Pattern::Rest(Some(name)) = &patterns[rest] {
+        // like a slice the rest is a new List or Tuple holding the same items
+        let middle = middle.iter().map(share).collect();
+        scope.insert(name.to_owned(), new_binding(&rest_kind(middle).into()));
+    }
+    for (pattern, item) in patterns[rest + 1..].iter().zip(tail) {
+        match_pattern(pattern, item, scope)?;
+    }
+    Ok(())
+}
+
+impl CodeState {
+    /// Finds the first arm whose pattern matches and whose guard is true,
+    /// along with the scope holding the names its pattern bound
+    pub(super) fn find_arm<'a>(&mut self, value: &VarRef, arms: &'a [MatchArm], scope: &VariableScope, position: (usize, u64, u64)) -> InterpResult<(&'a ArmBody, VariableScope)> {
+        for arm in arms {
+            let mut arm_scope = scope.clone();
+            if match_pattern(&arm.pattern, Rc::clone(value), &mut arm_scope).is_err() {continue;}
+            if let Some(guard) = &arm.guard {
+                if !self.variable_from_ast(guard, &arm_scope, position)?.borrow().to_bool() {continue;}
+            }
+            return Ok((&arm.body, arm_scope));
+        }
+        Err(InterpError(position, NoMatch(value.borrow().repr())))
+    }
+}
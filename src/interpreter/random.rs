@@ -0,0 +1,113 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{args::{arg_count, expect_int}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, variables::share, CodeState, VarRef, VarType, Variable};
+
+/// The xoshiro256** generator. It is fast and passes the usual statistical tests, but is not
+/// suitable for anything that needs to be unpredictable like passwords
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// The same seed always gives the same numbers
+    pub fn from_seed(seed: u64) -> Rng {
+        // spread the seed over the whole state with splitmix64, as an all zero state would only give zeros
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Rng { state: [next(), next(), next(), next()] }
+    }
+
+    /// Seeded from the time and process, so each run is different
+    pub fn from_entropy() -> Rng {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Rng::from_seed(time ^ ((std::process::id() as u64) << 32))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+
+    /// A Float from 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        // the top 53 bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number from 0 up to but not including `bound`, which cannot be 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // numbers past the last whole multiple of `bound` are thrown away so every result is as likely
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let x = self.next_u64();
+            if x < zone {return x % bound;}
+        }
+    }
+}
+
+impl CodeState {
+    pub(super) fn random_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "seed" => {
+                arg_count(args, 1, position)?;
+                self.rng = Rng::from_seed(expect_int(&args[0], position)? as u64);
+                Variable::None.into()
+            }
+            "random" => {
+                arg_count(args, 0, position)?;
+                Variable::Float(self.rng.next_f64()).into()
+            }
+            // both ends are included, like a dice roll `random_int(1, 6)`
+            "random_int" => {
+                arg_count(args, 2, position)?;
+                let (low, high) = (expect_int(&args[0], position)?, expect_int(&args[1], position)?);
+                if low > high {
+                    return Err(InterpError(position, EmptyRange(low, high)));
+                }
+                let offset = match (high.wrapping_sub(low) as u64).checked_add(1) {
+                    Some(size) => self.rng.below(size),
+                    // the range covers every Int
+                    None => self.rng.next_u64(),
+                };
+                Variable::Int(low.wrapping_add(offset as i64)).into()
+            }
+            // an item of a List or Tuple, or none when it is empty
+            "choice" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::List(ref items) | Variable::Tuple(ref items) => match items.len() {
+                        0 => Variable::None.into(),
+                        length => share(&items[self.rng.below(length as u64) as usize]),
+                    },
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
+                }
+            }
+            // shuffles the List in place
+            "shuffle" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow_mut() {
+                    Variable::List(ref mut items) => for i in (1..items.len()).rev() {
+                        let j = self.rng.below(i as u64 + 1) as usize;
+                        items.swap(i, j);
+                    },
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::List, x.to_type()))),
+                }
+                Variable::None.into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
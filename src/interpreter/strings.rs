@@ -0,0 +1,169 @@
+use super::{args::{arg_count, arg_count_in, expect_char, expect_int, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, CodeState, VarRef, VarType, Variable};
+
+/// Turns a possibly negative character index into one from the start, clamped to the string
+fn char_index(index: i64, length: usize) -> usize {
+    if index < 0 {
+        length.saturating_sub(index.unsigned_abs() as usize)
+    } else {
+        (index as usize).min(length)
+    }
+}
+
+/// Changing the case of a Char can give several characters, e.g. `ß` becomes `SS`
+fn single_char(s: String) -> Variable {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Variable::Char(c),
+        _ => Variable::String(s.into()),
+    }
+}
+
+/// Runs the test on a Char, or on every character of a non-empty String
+fn test_chars(arg: &VarRef, test: fn(&char) -> bool, position: (usize, u64, u64)) -> InterpResult<VarRef> {
+    Ok(match *arg.borrow() {
+        Variable::Char(c) => Variable::Bool(test(&c)),
+        Variable::String(ref s) => Variable::Bool(!s.is_empty() && s.chars().all(|c| test(&c))),
+        ref x => return Err(InterpError(position, IncorrectType(VarType::String, x.to_type()))),
+    }.into())
+}
+
+/// `s` repeated `count` times, or an error instead of running out of memory when that would be too long
+fn repeat(s: &str, count: usize, position: (usize, u64, u64)) -> InterpResult<String> {
+    if s.is_empty() {return Ok(String::new());}
+    let mut output = String::new();
+    match s.len().checked_mul(count) {
+        Some(length) if output.try_reserve_exact(length).is_ok() => {},
+        _ => return Err(InterpError(position, StringTooLong)),
+    }
+    for _ in 0..count {output.push_str(s);}
+    Ok(output)
+}
+
+impl CodeState {
+    pub(super) fn string_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            "upper" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::Char(c) => single_char(c.to_uppercase().collect()),
+                    _ => Variable::String(expect_string(&args[0], position)?.to_uppercase().into()),
+                }.into()
+            }
+            "lower" => {
+                arg_count(args, 1, position)?;
+                match *args[0].borrow() {
+                    Variable::Char(c) => single_char(c.to_lowercase().collect()),
+                    _ => Variable::String(expect_string(&args[0], position)?.to_lowercase().into()),
+                }.into()
+            }
+            "trim" => {
+                arg_count(args, 1, position)?;
+                Variable::String(expect_string(&args[0], position)?.trim().to_owned().into()).into()
+            }
+            "starts_with" => {
+                arg_count(args, 2, position)?;
+                let s = expect_string(&args[0], position)?;
+                Variable::Bool(s.starts_with(expect_string(&args[1], position)?.as_str())).into()
+            }
+            "ends_with" => {
+                arg_count(args, 2, position)?;
+                let s = expect_string(&args[0], position)?;
+                Variable::Bool(s.ends_with(expect_string(&args[1], position)?.as_str())).into()
+            }
+            "find" => {
+                arg_count(args, 2, position)?;
+                let s = expect_string(&args[0], position)?;
+                let pattern = match *args[1].borrow() {
+                    Variable::Char(c) => c.to_string().into(),
+                    _ => expect_string(&args[1], position)?,
+                };
+                // the character index of the first match, or -1 if there is none
+                match s.find(pattern.as_str()) {
+                    Some(byte_index) => Variable::Int(s.char_index(byte_index) as i64),
+                    None => Variable::Int(-1),
+                }.into()
+            }
+            "replace" => {
+                arg_count(args, 3, position)?;
+                let s = expect_string(&args[0], position)?;
+                let from = expect_string(&args[1], position)?;
+                let to = expect_string(&args[2], position)?;
+                Variable::String(s.replace(from.as_str(), &to).into()).into()
+            }
+            "join" => {
+                arg_count(args, 2, position)?;
+                let separator = expect_string(&args[1], position)?;
+                let Variable::List(ref list) = *args[0].borrow() else {
+                    return Err(InterpError(position, IncorrectType(VarType::List, args[0].borrow().to_type())));
+                };
+                let parts: Vec<String> = list.iter().map(|v| v.borrow().to_string()).collect();
+                Variable::String(parts.join(&separator).into()).into()
+            }
+            "substring" => {
+                arg_count_in(args, 2..=3, position)?;
+                let s = expect_string(&args[0], position)?;
+                let start = char_index(expect_int(&args[1], position)?, s.len());
+                let end = match args.get(2) {
+                    Some(end) => char_index(expect_int(end, position)?, s.len()),
+                    None => s.len(),
+                };
+                Variable::String(s.slice(start, end).into()).into()
+            }
+            "repeat" => {
+                arg_count(args, 2, position)?;
+                let s = expect_string(&args[0], position)?;
+                Variable::String(repeat(&s, expect_int(&args[1], position)?.max(0) as usize, position)?.into()).into()
+            }
+            "chars" => {
+                arg_count(args, 1, position)?;
+                let s = expect_string(&args[0], position)?;
+                Variable::List(s.chars().map(|c| Variable::Char(c).into()).collect()).into()
+            }
+            "lines" => {
+                arg_count(args, 1, position)?;
+                let s = expect_string(&args[0], position)?;
+                Variable::List(s.lines().map(|l| Variable::String(l.to_owned().into()).into()).collect()).into()
+            }
+            "ord" => {
+                arg_count(args, 1, position)?;
+                Variable::Int(expect_char(&args[0], position)? as i64).into()
+            }
+            "chr" => {
+                arg_count(args, 1, position)?;
+                let code = expect_int(&args[0], position)?;
+                match u32::try_from(code).ok().and_then(char::from_u32) {
+                    Some(c) => Variable::Char(c).into(),
+                    None => return Err(InterpError(position, InvalidChar(code))),
+                }
+            }
+            "pad_left" | "pad_right" => {
+                arg_count_in(args, 2..=3, position)?;
+                let s = expect_string(&args[0], position)?;
+                let width = expect_int(&args[1], position)?.max(0) as usize;
+                let fill = match args.get(2) {
+                    Some(fill) => expect_char(fill, position)?,
+                    None => ' ',
+                };
+                let padding = repeat(&fill.to_string(), width.saturating_sub(s.len()), position)?;
+                if function_name == "pad_left" {
+                    Variable::String((padding + &s).into()).into()
+                } else {
+                    Variable::String((s.to_string() + &padding).into()).into()
+                }
+            }
+            "is_digit" => {
+                arg_count(args, 1, position)?;
+                test_chars(&args[0], char::is_ascii_digit, position)?
+            }
+            "is_alpha" => {
+                arg_count(args, 1, position)?;
+                test_chars(&args[0], |c| c.is_alphabetic(), position)?
+            }
+            "is_space" => {
+                arg_count(args, 1, position)?;
+                test_chars(&args[0], |c| c.is_whitespace(), position)?
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
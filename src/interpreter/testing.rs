@@ -0,0 +1,82 @@
+use std::{rc::Rc, thread};
+
+use crate::ast::{ASTree, EnumDecleration, FunctionDecleration, Op};
+
+use super::{args::{arg_count, arg_count_in}, interpreter_error::{InterpError, InterpResult, InterpErrorType::*}, operations::variable_operation, CodeState, VarRef, Variable};
+
+/// Functions declared with `test func` are tests, and so are ones whose name starts with `test_` if they take no
+/// arguments, as ones that do are helpers like `test_sudoku(board)`
+fn is_test(function: &FunctionDecleration) -> bool {
+    function.is_test || (function.name.starts_with("test_") && function.args.is_empty())
+}
+
+/// Compares with `==` where there is one, so `1 == 1.0`, and item by item for Lists, Hashes and the like
+fn values_equal(x: &VarRef, y: &VarRef) -> bool {
+    match variable_operation(Rc::clone(x), Rc::clone(y), Op::Equality) {
+        Ok(result) => result.borrow().to_bool(),
+        Err(_) => *x.borrow() == *y.borrow(),
+    }
+}
+
+impl CodeState {
+    pub(super) fn assert_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        Ok(Some(match function_name {
+            // `assert(condition)` or `assert(condition, message)`
+            "assert" => {
+                arg_count_in(args, 1..=2, position)?;
+                if !args[0].borrow().to_bool() {
+                    return Err(InterpError(position, AssertionFailed(args.get(1).map(|m| m.borrow().to_string()))));
+                }
+                Variable::None.into()
+            }
+            "assert_eq" => {
+                arg_count(args, 2, position)?;
+                if !values_equal(&args[0], &args[1]) {
+                    return Err(InterpError(position, NotEqual(args[0].borrow().repr(), args[1].borrow().repr())));
+                }
+                Variable::None.into()
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// Runs every test and prints which ones failed and where, then gives back whether they all passed.
+/// Each test gets a new interpreter so changes one test makes to globals are not seen by the others.
+/// `paths` are the files the positions in errors refer to
+pub fn run_tests(functions: Vec<FunctionDecleration>, enums: Vec<EnumDecleration>, globals: Vec<Vec<ASTree>>, modules: Vec<String>, paths: Vec<String>) -> bool {
+    let tests: Vec<String> = functions.iter().filter(|f| is_test(f)).map(|f| f.name.clone()).collect();
+    // imported files are set up before the files that import them
+    let globals: Vec<ASTree> = globals.into_iter().rev().flatten().collect();
+
+    println!("running {} test{}", tests.len(), if tests.len() == 1 {""} else {"s"});
+    let mut failures = Vec::new();
+    for test in &tests {
+        let (functions, enums, globals, modules, test_name) = (functions.clone(), enums.clone(), globals.clone(), modules.clone(), test.clone());
+        // on its own thread a panic in the interpreter only fails this test
+        let result = thread::Builder::new().stack_size(8 * 1024 * 1024).spawn(move || {
+            let mut code_state = CodeState::new(functions, enums, modules);
+            let result = code_state.run_globals(&globals).and_then(|_| code_state.run_function(&test_name, &Vec::new(), (0, 0, 0)));
+            // errors hold `Rc`s so only the message is sent back
+            result.map(|_| ()).map_err(|InterpError(position, error)| (position, error.to_string()))
+        }).unwrap().join();
+
+        let failure = match result {
+            Ok(Ok(())) => None,
+            Ok(Err(((file, line, column), message))) => Some(format!("at {}:{line}:{column}\n{message}", paths[file])),
+            Err(_) => Some("the interpreter panicked".to_owned()),
+        };
+        println!("test {test} ... {}", if failure.is_some() {"FAILED"} else {"ok"});
+        if let Some(failure) = failure {failures.push((test, failure));}
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (test, failure) in &failures {
+            println!("\n---- {test} {failure}");
+        }
+    }
+    let passed = tests.len() - failures.len();
+    println!("\ntest result: {}. {passed} passed; {} failed", if failures.is_empty() {"ok"} else {"FAILED"}, failures.len());
+    failures.is_empty()
+}
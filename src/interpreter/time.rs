@@ -0,0 +1,289 @@
+use std::{thread, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use super::{args::{arg_count, arg_count_in, expect_number, expect_string}, interpreter_error::{InterpError, InterpResult, InterpErrorType::{self, *}}, variables::{BeetleHash, HashKey}, CodeState, VarRef, VarType, Variable};
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+
+/// Used by `format_date` and `parse_date` when no format is given, e.g. `2024-03-09T14:05:00Z`
+const ISO_8601: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Years further from 0 than this are out of range, so working out their timestamps cannot overflow
+const MAX_YEAR: i64 = 10_000_000;
+
+/// A moment in UTC broken into its parts
+struct Date {
+    year: i64,
+    /// From 1 to 12
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millisecond: i64,
+}
+
+/// The days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+/// From Howard Hinnant's `days_from_civil`, it counts in 400 year eras starting on the 1st of March
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 {month - 3} else {month + 9}) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 {month + 3} else {month - 9};
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    fn from_timestamp(timestamp: f64) -> Result<Date, InterpErrorType> {
+        if !timestamp.is_finite() {return Err(InvalidDate(format!("timestamp {timestamp} is not a number of seconds")));}
+        // a huge timestamp saturates here, which still gives a year that is out of range
+        let milliseconds = (timestamp * 1000.0).floor() as i64;
+        let seconds = milliseconds.div_euclid(1000);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        if year.abs() > MAX_YEAR {return Err(InvalidDate(format!("timestamp {timestamp:e} is out of range")));}
+        let second_of_day = seconds.rem_euclid(86400);
+        Ok(Date {
+            year, month, day,
+            hour: second_of_day / 3600,
+            minute: second_of_day / 60 % 60,
+            second: second_of_day % 60,
+            millisecond: milliseconds.rem_euclid(1000),
+        })
+    }
+
+    fn timestamp(&self) -> f64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        (days * 86400 + self.hour * 3600 + self.minute * 60 + self.second) as f64 + self.millisecond as f64 / 1000.0
+    }
+
+    /// 0 for Monday up to 6 for Sunday
+    fn weekday(&self) -> usize {
+        // 1970-01-01 was a Thursday
+        (days_from_civil(self.year, self.month, self.day) + 3).rem_euclid(7) as usize
+    }
+
+    fn day_of_year(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) - days_from_civil(self.year, 1, 1) + 1
+    }
+
+    /// Gives the reason the parts do not make up a real date
+    fn validate(&self) -> Result<(), String> {
+        let in_range = |name: &str, value: i64, high: i64| {
+            if (0..=high).contains(&value) {Ok(())} else {Err(format!("{name} {value} is out of range"))}
+        };
+        if self.year.abs() > MAX_YEAR {return Err(format!("year {} is out of range", self.year));}
+        if !(1..=12).contains(&self.month) {return Err(format!("month {} is out of range", self.month));}
+        if !(1..=days_in_month(self.year, self.month)).contains(&self.day) {
+            return Err(format!("day {} is out of range for {} {}", self.day, MONTHS[self.month as usize - 1], self.year));
+        }
+        in_range("hour", self.hour, 23)?;
+        in_range("minute", self.minute, 59)?;
+        in_range("second", self.second, 59)?;
+        in_range("millisecond", self.millisecond, 999)
+    }
+
+    fn to_hash(&self) -> BeetleHash {
+        let mut hash = BeetleHash::new();
+        for (name, value) in [
+            ("year", self.year), ("month", self.month), ("day", self.day),
+            ("hour", self.hour), ("minute", self.minute), ("second", self.second), ("millisecond", self.millisecond),
+            ("weekday", self.weekday() as i64 + 1), ("yearday", self.day_of_year()),
+        ] {
+            hash.insert(name.into(), Variable::Int(value).into());
+        }
+        hash
+    }
+
+    /// The date in a Hash like the ones `date` gives, where every part but the year can be left out
+    fn from_hash(hash: &BeetleHash) -> Result<Date, InterpErrorType> {
+        let part = |name: &str, default: Option<i64>| match hash.get(&HashKey::from(name)).map(|v| v.borrow().clone()) {
+            Some(Variable::Int(i)) => Ok(i),
+            Some(x) => Err(IncorrectType(VarType::Int, x.to_type())),
+            None => default.ok_or_else(|| InvalidDate(format!("a date needs a {name}"))),
+        };
+        let date = Date {
+            year: part("year", None)?,
+            month: part("month", Some(1))?,
+            day: part("day", Some(1))?,
+            hour: part("hour", Some(0))?,
+            minute: part("minute", Some(0))?,
+            second: part("second", Some(0))?,
+            millisecond: part("millisecond", Some(0))?,
+        };
+        date.validate().map_err(InvalidDate)?;
+        Ok(date)
+    }
+
+    /// Writes the date following a format like `strftime`'s
+    fn format(&self, format: &str) -> Result<String, String> {
+        let mut output = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => output.push_str(&format!("{:04}", self.year)),
+                Some('m') => output.push_str(&format!("{:02}", self.month)),
+                Some('d') => output.push_str(&format!("{:02}", self.day)),
+                Some('H') => output.push_str(&format!("{:02}", self.hour)),
+                Some('M') => output.push_str(&format!("{:02}", self.minute)),
+                Some('S') => output.push_str(&format!("{:02}", self.second)),
+                Some('f') => output.push_str(&format!("{:03}", self.millisecond)),
+                Some('j') => output.push_str(&format!("{:03}", self.day_of_year())),
+                Some('A') => output.push_str(WEEKDAYS[self.weekday()]),
+                Some('a') => output.push_str(&WEEKDAYS[self.weekday()][..3]),
+                Some('B') => output.push_str(MONTHS[self.month as usize - 1]),
+                Some('b') => output.push_str(&MONTHS[self.month as usize - 1][..3]),
+                Some('%') => output.push('%'),
+                Some(c) => return Err(format!("unknown date directive `%{c}`")),
+                None => return Err("format ends with `%`".to_owned()),
+            }
+        }
+        Ok(output)
+    }
+
+    /// Reads a date written in the format, or gives `None` if the text does not match it.
+    /// Parts missing from the format default to the start of 1970
+    fn parse(text: &str, format: &str) -> Result<Option<Date>, String> {
+        let mut date = Date { year: 1970, month: 1, day: 1, hour: 0, minute: 0, second: 0, millisecond: 0 };
+        let text: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        // takes up to `max` digits, with a sign allowed for years
+        let number = |i: &mut usize, max: usize, signed: bool| -> Option<i64> {
+            let start = *i;
+            if signed && matches!(text.get(*i), Some('-' | '+')) {*i += 1;}
+            let digits_start = *i;
+            while *i < text.len() && *i - digits_start < max && text[*i].is_ascii_digit() {*i += 1;}
+            if *i == digits_start {return None;}
+            Some(text[start..*i].iter().collect::<String>().parse().unwrap())
+        };
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                if text.get(i) != Some(&c) {return Ok(None);}
+                i += 1;
+                continue;
+            }
+            let parsed = match chars.next() {
+                Some('Y') => number(&mut i, 9, true).map(|n| date.year = n),
+                Some('m') => number(&mut i, 2, false).map(|n| date.month = n),
+                Some('d') => number(&mut i, 2, false).map(|n| date.day = n),
+                Some('H') => number(&mut i, 2, false).map(|n| date.hour = n),
+                Some('M') => number(&mut i, 2, false).map(|n| date.minute = n),
+                Some('S') => number(&mut i, 2, false).map(|n| date.second = n),
+                Some('f') => number(&mut i, 3, false).map(|n| date.millisecond = n),
+                Some('B' | 'b') => {
+                    let rest: String = text[i..].iter().collect::<String>().to_lowercase();
+                    // full names are tried first so `%b` does not stop partway through one
+                    let found = MONTHS.iter().enumerate()
+                        .find_map(|(m, name)| rest.starts_with(&name.to_lowercase()).then_some((m, name.len())))
+                        .or_else(|| MONTHS.iter().enumerate().find_map(|(m, name)| rest.starts_with(&name[..3].to_lowercase()).then_some((m, 3))));
+                    found.map(|(month, length)| {
+                        date.month = month as i64 + 1;
+                        i += length;
+                    })
+                },
+                Some('%') if text.get(i) == Some(&'%') => {i += 1; Some(())},
+                Some('%') => None,
+                Some(c) => return Err(format!("unknown date directive `%{c}`")),
+                None => return Err("format ends with `%`".to_owned()),
+            };
+            if parsed.is_none() {return Ok(None);}
+        }
+        Ok((i == text.len() && date.validate().is_ok()).then_some(date))
+    }
+}
+
+impl CodeState {
+    pub(super) fn time_function(&mut self, function_name: &str, args: &[VarRef], position: (usize, u64, u64)) -> InterpResult<Option<VarRef>> {
+        // the format is the optional last argument of `format_date` and `parse_date`
+        let date_format = || match args.get(1) {
+            Some(format) => expect_string(format, position).map(|s| s.to_string()),
+            None => Ok(ISO_8601.to_owned()),
+        };
+        Ok(Some(match function_name {
+            // seconds since the Unix epoch
+            "now" => {
+                arg_count(args, 0, position)?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| InterpError(position, Io(e.to_string())))?;
+                Variable::Float(now.as_secs_f64()).into()
+            }
+            // seconds since the program started, it never goes backwards so it is the one to time things with
+            "monotonic" => {
+                arg_count(args, 0, position)?;
+                Variable::Float(self.started.elapsed().as_secs_f64()).into()
+            }
+            "sleep" => {
+                arg_count(args, 1, position)?;
+                let milliseconds = expect_number(&args[0], position)?;
+                if milliseconds > 0.0 {
+                    let duration = Duration::try_from_secs_f64(milliseconds / 1000.0).map_err(|_| InterpError(position, InvalidDuration(milliseconds)))?;
+                    thread::sleep(duration);
+                }
+                Variable::None.into()
+            }
+            // the parts of a timestamp in UTC
+            "date" => {
+                arg_count(args, 1, position)?;
+                let date = Date::from_timestamp(expect_number(&args[0], position)?).map_err(|e| InterpError(position, e))?;
+                Variable::Hash(date.to_hash()).into()
+            }
+            // the timestamp of a Hash of date parts, the reverse of `date`
+            "timestamp" => {
+                arg_count(args, 1, position)?;
+                let date = match *args[0].borrow() {
+                    Variable::Hash(ref hash) => Date::from_hash(hash).map_err(|e| InterpError(position, e))?,
+                    ref x => return Err(InterpError(position, IncorrectType(VarType::Hash, x.to_type()))),
+                };
+                Variable::Float(date.timestamp()).into()
+            }
+            "format_date" => {
+                arg_count_in(args, 1..=2, position)?;
+                let date = Date::from_timestamp(expect_number(&args[0], position)?).map_err(|e| InterpError(position, e))?;
+                match date.format(&date_format()?) {
+                    Ok(text) => Variable::String(text.into()).into(),
+                    Err(message) => return Err(InterpError(position, InvalidFormat(message))),
+                }
+            }
+            // the timestamp of a date written in the format, or none if it does not match
+            "parse_date" => {
+                arg_count_in(args, 1..=2, position)?;
+                match Date::parse(&expect_string(&args[0], position)?, &date_format()?) {
+                    Ok(Some(date)) => Variable::Float(date.timestamp()).into(),
+                    Ok(None) => Variable::None.into(),
+                    Err(message) => return Err(InterpError(position, InvalidFormat(message))),
+                }
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
@@ -1,6 +1,10 @@
-use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, fmt::Display, hash::Hash, ops::Deref, rc::Rc};
 
-#[derive(Debug, Clone, Copy)]
+use crate::bigint::BigInt;
+
+use super::gc;
+
+#[derive(Debug, Clone)]
 #[derive(PartialEq)]
 pub enum VarType {
     None,
@@ -11,7 +15,12 @@ pub enum VarType {
     String,
     Type,
     List,
-    Hash
+    Tuple,
+    Hash,
+    Set,
+    Socket,
+    /// The type of the variants of a declared enum, named after it
+    Enum(Rc<str>),
 }
 
 impl Display for VarType {
@@ -25,7 +34,11 @@ impl Display for VarType {
             VarType::String => write!(f, "String"),
             VarType::Type => write!(f, "Type"),
             VarType::List => write!(f, "List"),
+            VarType::Tuple => write!(f, "Tuple"),
             VarType::Hash => write!(f, "Hash"),
+            VarType::Set => write!(f, "Set"),
+            VarType::Socket => write!(f, "Socket"),
+            VarType::Enum(name) => write!(f, "{name}"),
         }
     }
 }
@@ -36,12 +49,32 @@ pub enum Variable {
     None,
     Bool(bool),
     Int(i64),
+    /// An Int too large for an `i64`, arithmetic on Ints switches to these when it would overflow
+    BigInt(BigInt),
     Float(f64),
     Char(char),
-    String(String),
+    String(BeetleString),
     Type(VarType),
     List(Vec<VarRef>),
-    Hash(Box<HashMap<String, VarRef>>),
+    /// A fixed list of values that cannot be changed once made
+    Tuple(Vec<VarRef>),
+    Hash(BeetleHash),
+    Set(BeetleSet),
+    /// A handle id into the interpreter's open sockets
+    Socket(usize),
+    Enum(EnumValue),
+}
+
+/// A variant of a declared enum, along with any fields it holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    pub name: Rc<str>,
+    pub variant: Rc<str>,
+    pub fields: Vec<VarRef>,
+    /// The names of the fields of a `Variant { field }`, empty for the other kinds of variant
+    pub field_names: Rc<[String]>,
+    /// Whether it was declared as `Variant(...)`, so `Variant()` can be told apart from `Variant`
+    pub is_tuple: bool,
 }
 
 impl Display for Variable {
@@ -50,57 +83,456 @@ impl Display for Variable {
             Variable::None => Display::fmt("None", f),
             Variable::Bool(bool) => Display::fmt(bool, f),
             Variable::Int(int) => Display::fmt(int, f),
+            Variable::BigInt(int) => Display::fmt(int, f),
             Variable::Float(float) => Display::fmt(float, f),
             Variable::Char(char) => Display::fmt(char, f),
             Variable::String(string) => Display::fmt(string, f),
             Variable::Type(var_type) => Display::fmt(var_type, f),
-            Variable::List(vec) => {
-                Display::fmt("[", f)?;
-                for (i, v) in vec.iter().enumerate() {
-                    Display::fmt(&v.borrow(), f)?;
-                    if i != vec.len()-1 {write!(f, ", ")?;}
-                }
-                Display::fmt("]", f)
-            },
-            Variable::Hash(hash) => {
-                Display::fmt("{", f)?;
-                for (i, (k, v)) in hash.iter().enumerate() {
-                    Display::fmt(&k, f)?;
-                    Display::fmt(": ", f)?;
-                    Display::fmt(&v.borrow(), f)?;
-                    if i != hash.len()-1 {write!(f, ", ")?;}
-                }
-                Display::fmt("}", f)
+            // the elements of lists and hashes are shown with their repr so `["1", 1]` is not printed as `[1, 1]`
+            Variable::List(_) | Variable::Tuple(_) | Variable::Hash(_) | Variable::Set(_) => {
+                let mut output = String::new();
+                self.write_repr(&mut output, &mut Vec::new(), None);
+                f.write_str(&output)
             },
+            Variable::Socket(id) => write!(f, "<socket {id}>"),
+            Variable::Enum(_) => f.write_str(&self.repr()),
         }
     }
 }
 
+/// Lines of a pretty printed repr longer than this are split up
+const REPR_WIDTH: usize = 80;
+
 impl Variable {
+    /// Formats the value the way it would be written in code, splitting large lists and hashes across lines
+    pub fn repr(&self) -> String {
+        let mut output = String::new();
+        self.write_repr(&mut output, &mut Vec::new(), Some(0));
+        output
+    }
+    /// Writes the repr of the value, `depth` is the indentation level when pretty printing or `None` to keep it on one line.
+    /// `seen` holds the lists and hashes currently being written so a value that contains itself is shown as `[...]`
+    fn write_repr(&self, output: &mut String, seen: &mut Vec<*const Variable>, depth: Option<usize>) {
+        let pointer = self as *const Variable;
+        match self {
+            Variable::None => output.push_str("none"),
+            Variable::Char(char) => output.push_str(&format!("{char:?}")),
+            Variable::String(string) => output.push_str(&format!("{string:?}")),
+            Variable::Float(float) => output.push_str(&format!("{float:?}")),
+            Variable::List(_) if seen.contains(&pointer) => output.push_str("[...]"),
+            Variable::Tuple(_) if seen.contains(&pointer) => output.push_str("(...)"),
+            Variable::Hash(_) if seen.contains(&pointer) => output.push_str("{...}"),
+            // `{}` is an empty Hash
+            Variable::Set(set) if set.is_empty() => output.push_str("set()"),
+            Variable::List(_) | Variable::Tuple(_) | Variable::Hash(_) | Variable::Set(_) => {
+                seen.push(pointer);
+                let mut items = Vec::new();
+                match self {
+                    Variable::List(vec) | Variable::Tuple(vec) => for v in vec {
+                        let mut item = String::new();
+                        v.borrow().write_repr(&mut item, seen, None);
+                        items.push(item);
+                    },
+                    Variable::Hash(hash) => for (k, v) in hash.iter() {
+                        let mut item = format!("{}: ", k.to_variable().repr());
+                        v.borrow().write_repr(&mut item, seen, None);
+                        items.push(item);
+                    },
+                    Variable::Set(set) => for k in set.iter() {
+                        items.push(k.to_variable().repr());
+                    },
+                    _ => unreachable!(),
+                }
+                let (open, close) = match self {
+                    Variable::List(_) => ("[", "]"),
+                    Variable::Tuple(_) => ("(", ")"),
+                    _ => ("{", "}"),
+                };
+
+                let mut one_line = items.join(", ");
+                // a tuple of one item keeps its comma so it is not mistaken for brackets
+                if let Variable::Tuple(vec) = self {
+                    if vec.len() == 1 {one_line.push(',');}
+                }
+                match depth {
+                    Some(depth) if (depth + 1) * 4 + one_line.len() > REPR_WIDTH => {
+                        // redo each item with its own line, pretty printing anything nested
+                        let indent = "    ".repeat(depth + 1);
+                        output.push_str(open);
+                        output.push('\n');
+                        let mut write_item = |prefix: String, v: &VarRef| {
+                            output.push_str(&indent);
+                            output.push_str(&prefix);
+                            v.borrow().write_repr(output, seen, Some(depth + 1));
+                            output.push_str(",\n");
+                        };
+                        match self {
+                            Variable::List(vec) | Variable::Tuple(vec) => for v in vec {write_item(String::new(), v)},
+                            Variable::Hash(hash) => for (k, v) in hash.iter() {write_item(format!("{}: ", k.to_variable().repr()), v)},
+                            Variable::Set(set) => for k in set.iter() {write_item(String::new(), &k.to_variable().into())},
+                            _ => unreachable!(),
+                        }
+                        output.push_str(&"    ".repeat(depth));
+                        output.push_str(close);
+                    }
+                    _ => {
+                        output.push_str(open);
+                        output.push_str(&one_line);
+                        output.push_str(close);
+                    }
+                }
+                seen.pop();
+            },
+            Variable::Enum(value) => {
+                output.push_str(&format!("{}::{}", value.name, value.variant));
+                let mut items = Vec::new();
+                for (i, v) in value.fields.iter().enumerate() {
+                    let mut item = match value.field_names.get(i) {
+                        Some(name) => format!("{name}: "),
+                        None => String::new(),
+                    };
+                    v.borrow().write_repr(&mut item, seen, None);
+                    items.push(item);
+                }
+                if !value.field_names.is_empty() {
+                    output.push_str(&format!(" {{{}}}", items.join(", ")));
+                } else if value.is_tuple {
+                    output.push_str(&format!("({})", items.join(", ")));
+                }
+            },
+            x => output.push_str(&x.to_string()),
+        }
+    }
     pub fn to_bool(&self) -> bool {
         match self {
             Variable::None => false,
             Variable::Bool(bool) => *bool,
             Variable::Int(int) => *int != 0,
+            Variable::BigInt(_) => true,
             Variable::Float(float) => *float != 0.0,
             Variable::Char(char) => *char as u32 != 0,
             Variable::String(string) => !string.is_empty(),
             Variable::Type(_) => true,
-            Variable::List(vec) => !vec.is_empty(),
+            Variable::List(vec) | Variable::Tuple(vec) => !vec.is_empty(),
             Variable::Hash(hash) => !hash.is_empty(),
+            Variable::Set(set) => !set.is_empty(),
+            Variable::Socket(_) => true,
+            Variable::Enum(_) => true,
         }
     }
     pub fn to_type(&self) -> VarType {
         match self {
             Variable::None => VarType::None,
             Variable::Bool(_) => VarType::Bool,
-            Variable::Int(_) => VarType::Int,
+            Variable::Int(_) | Variable::BigInt(_) => VarType::Int,
             Variable::Float(_) => VarType::Float,
             Variable::Char(_) => VarType::Char,
             Variable::String(_) => VarType::String,
             Variable::Type(_) => VarType::Type,
             Variable::List(_) => VarType::List,
+            Variable::Tuple(_) => VarType::Tuple,
             Variable::Hash(_) => VarType::Hash,
+            Variable::Set(_) => VarType::Set,
+            Variable::Socket(_) => VarType::Socket,
+            Variable::Enum(value) => VarType::Enum(Rc::clone(&value.name)),
+        }
+    }
+}
+
+/// An immutable string indexed by character rather than by byte.
+///
+/// The text is shared so copying a string is cheap, and non-ASCII strings keep the byte offset
+/// of every character so indexing, slicing and `len` all take constant time.
+#[derive(Clone)]
+pub struct BeetleString {
+    text: Rc<str>,
+    /// The byte offset of each character, left empty when the text is ASCII and every character is one byte
+    offsets: Rc<[usize]>,
+}
+
+impl BeetleString {
+    /// The number of characters in the string
+    pub fn len(&self) -> usize {
+        if self.offsets.is_empty() {self.text.len()} else {self.offsets.len()}
+    }
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+    /// The byte offset of the character at `index`, or the length in bytes if it is past the end
+    fn byte_offset(&self, index: usize) -> usize {
+        if self.offsets.is_empty() {index.min(self.text.len())}
+        else {self.offsets.get(index).copied().unwrap_or(self.text.len())}
+    }
+    /// The character index of the character starting at `byte_offset`
+    pub fn char_index(&self, byte_offset: usize) -> usize {
+        if self.offsets.is_empty() {byte_offset}
+        else {self.offsets.partition_point(|o| *o < byte_offset)}
+    }
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        if index >= self.len() {return None;}
+        self.text[self.byte_offset(index)..].chars().next()
+    }
+    /// The characters from `start` up to but not including `end`, both clamped to the string
+    pub fn slice(&self, start: usize, end: usize) -> &str {
+        let start = self.byte_offset(start);
+        let end = self.byte_offset(end).max(start);
+        &self.text[start..end]
+    }
+    /// A copy of the string with the character at `index` replaced, or `None` if it is out of range
+    pub fn with_char(&self, index: usize, c: char) -> Option<BeetleString> {
+        if index >= self.len() {return None;}
+        let mut new_string = String::with_capacity(self.text.len() + 4);
+        new_string.push_str(self.slice(0, index));
+        new_string.push(c);
+        new_string.push_str(self.slice(index + 1, self.len()));
+        Some(new_string.into())
+    }
+}
+
+impl From<String> for BeetleString {
+    fn from(value: String) -> Self {
+        let offsets = if value.is_ascii() {Rc::from([])} else {value.char_indices().map(|(i, _)| i).collect()};
+        BeetleString { text: value.into(), offsets }
+    }
+}
+
+impl From<&str> for BeetleString {
+    fn from(value: &str) -> Self {
+        value.to_owned().into()
+    }
+}
+
+impl Deref for BeetleString {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl PartialEq for BeetleString {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for BeetleString {}
+
+impl Hash for BeetleString {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+impl std::fmt::Debug for BeetleString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.text, f)
+    }
+}
+
+impl Display for BeetleString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&*self.text, f)
+    }
+}
+
+/// The values that can be used as the key of a Hash.
+///
+/// Floats are left out as NaN is not equal to itself, and Lists and Hashes as they can change after being inserted.
+/// Tuples can be keys when everything in them can be
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    None,
+    Bool(bool),
+    Int(i64),
+    BigInt(BigInt),
+    Char(char),
+    String(BeetleString),
+    Tuple(Vec<HashKey>),
+}
+
+impl HashKey {
+    /// The key for a value, or `None` if the value cannot be hashed
+    pub fn from_variable(value: &Variable) -> Option<HashKey> {
+        Some(match value {
+            Variable::None => HashKey::None,
+            Variable::Bool(b) => HashKey::Bool(*b),
+            Variable::Int(i) => HashKey::Int(*i),
+            Variable::BigInt(i) => HashKey::BigInt(i.clone()),
+            Variable::Char(c) => HashKey::Char(*c),
+            Variable::String(s) => HashKey::String(s.clone()),
+            Variable::Tuple(t) => HashKey::Tuple(t.iter().map(|v| HashKey::from_variable(&v.borrow())).collect::<Option<_>>()?),
+            _ => return None,
+        })
+    }
+    pub fn to_variable(&self) -> Variable {
+        match self {
+            HashKey::None => Variable::None,
+            HashKey::Bool(b) => Variable::Bool(*b),
+            HashKey::Int(i) => Variable::Int(*i),
+            HashKey::BigInt(i) => Variable::BigInt(i.clone()),
+            HashKey::Char(c) => Variable::Char(*c),
+            HashKey::String(s) => Variable::String(s.clone()),
+            HashKey::Tuple(t) => Variable::Tuple(t.iter().map(|k| k.to_variable().into()).collect()),
+        }
+    }
+}
+
+impl From<&str> for HashKey {
+    fn from(value: &str) -> Self {
+        HashKey::String(value.into())
+    }
+}
+
+impl From<String> for HashKey {
+    fn from(value: String) -> Self {
+        HashKey::String(value.into())
+    }
+}
+
+impl Display for HashKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_variable(), f)
+    }
+}
+
+/// A hash map that remembers the order its keys were first inserted in, so iterating over it is deterministic
+#[derive(Debug, Clone, Default)]
+pub struct BeetleHash {
+    entries: Vec<(HashKey, VarRef)>,
+    /// The position of each key in `entries`
+    index: HashMap<HashKey, usize>,
+}
+
+impl BeetleHash {
+    pub fn new() -> Self {
+        BeetleHash::default()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn get(&self, key: &HashKey) -> Option<&VarRef> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+    pub fn contains_key(&self, key: &HashKey) -> bool {
+        self.index.contains_key(key)
+    }
+    /// Sets the value of a key, a key that is already in the hash keeps its place in the order
+    pub fn insert(&mut self, key: HashKey, value: VarRef) -> Option<VarRef> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+    pub fn remove(&mut self, key: &HashKey) -> Option<VarRef> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (k, _) in &self.entries[i..] {
+            *self.index.get_mut(k).unwrap() -= 1;
+        }
+        Some(value)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&HashKey, &VarRef)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &HashKey> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &VarRef> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl FromIterator<(HashKey, VarRef)> for BeetleHash {
+    fn from_iter<T: IntoIterator<Item = (HashKey, VarRef)>>(iter: T) -> Self {
+        let mut hash = BeetleHash::new();
+        for (k, v) in iter {
+            hash.insert(k, v);
+        }
+        hash
+    }
+}
+
+/// Two hashes are equal when they have the same keys and values, whatever order they were inserted in
+impl PartialEq for BeetleHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+/// A set of hashable values that, like a Hash, remembers the order they were added in
+#[derive(Debug, Clone, Default)]
+pub struct BeetleSet {
+    items: Vec<HashKey>,
+    /// The position of each item in `items`
+    index: HashMap<HashKey, usize>,
+}
+
+impl BeetleSet {
+    pub fn new() -> Self {
+        BeetleSet::default()
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    pub fn contains(&self, item: &HashKey) -> bool {
+        self.index.contains_key(item)
+    }
+    /// Adds an item, returns false if it was already in the set
+    pub fn insert(&mut self, item: HashKey) -> bool {
+        if self.contains(&item) {return false;}
+        self.index.insert(item.clone(), self.items.len());
+        self.items.push(item);
+        true
+    }
+    /// Removes an item, returns false if it was not in the set
+    pub fn remove(&mut self, item: &HashKey) -> bool {
+        let Some(i) = self.index.remove(item) else {return false};
+        self.items.remove(i);
+        for k in &self.items[i..] {
+            *self.index.get_mut(k).unwrap() -= 1;
+        }
+        true
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &HashKey> {
+        self.items.iter()
+    }
+}
+
+impl FromIterator<HashKey> for BeetleSet {
+    fn from_iter<T: IntoIterator<Item = HashKey>>(iter: T) -> Self {
+        let mut set = BeetleSet::new();
+        for item in iter {
+            set.insert(item);
+        }
+        set
+    }
+}
+
+impl PartialEq for BeetleSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|k| other.contains(k))
+    }
+}
+
+/// Ints only use a `BigInt` when they do not fit in an `i64`, so each number has one representation
+impl From<BigInt> for Variable {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(i) => Variable::Int(i),
+            None => Variable::BigInt(value),
         }
     }
 }
@@ -109,28 +541,64 @@ pub type VarRef = Rc<RefCell<Variable>>;
 
 impl From<Variable> for Rc<RefCell<Variable>> {
     fn from(value: Variable) -> Self {
-        Rc::new(RefCell::new(value))
+        let value = Rc::new(RefCell::new(value));
+        gc::track(&value);
+        value
     }
 }
 
+/// The slot a name is bound to. Assigning to the name puts a new value in the slot,
+/// so other names and collections holding the old value do not see the change
+pub type Binding = Rc<RefCell<VarRef>>;
+
+/// How a value is stored when it is bound to a name, passed to a function or put in a collection.
+/// Lists, Hashes and Sets are shared so changes made through one name are seen through all of them,
+/// everything else is copied and behaves as a plain value
+pub fn share(value: &VarRef) -> VarRef {
+    match *value.borrow() {
+        Variable::List(_) | Variable::Hash(_) | Variable::Set(_) => Rc::clone(value),
+        ref x => x.clone().into(),
+    }
+}
+
+pub fn new_binding(value: &VarRef) -> Binding {
+    Rc::new(RefCell::new(share(value)))
+}
+
 pub fn deep_copy(item: &VarRef) -> VarRef {
-    match *item.borrow() {
+    deep_copy_with(item, &mut HashMap::new())
+}
+
+/// `copies` maps each List, Tuple, Hash and enum variant already copied to its copy,
+/// so a value that contains itself is copied as one that contains the copy
+fn deep_copy_with(item: &VarRef, copies: &mut HashMap<*const RefCell<Variable>, VarRef>) -> VarRef {
+    if let Some(copy) = copies.get(&Rc::as_ptr(item)) {return Rc::clone(copy);}
+    let value = match *item.borrow() {
         Variable::None => Variable::None,
         Variable::Bool(x) => Variable::Bool(x),
         Variable::Int(x) => Variable::Int(x),
+        Variable::BigInt(ref x) => Variable::BigInt(x.clone()),
         Variable::Float(x) => Variable::Float(x),
         Variable::Char(x) => Variable::Char(x),
         Variable::String(ref x) => Variable::String(x.clone()),
-        Variable::Type(var_type) => Variable::Type(var_type),
-        Variable::List(ref vec) => Variable::List(vec.iter().map(|i| deep_copy(i)).collect()),
-        Variable::Hash(ref hash) => {
-            let mut new_hash = HashMap::new();
-            
-            for (k, v) in hash.iter() {
-                new_hash.insert(k.to_owned(), deep_copy(v));
-            }
-
-            Variable::Hash(Box::new(new_hash)).into()
+        Variable::Type(ref var_type) => Variable::Type(var_type.clone()),
+        Variable::Set(ref set) => Variable::Set(set.clone()),
+        Variable::Socket(id) => Variable::Socket(id),
+        Variable::List(_) | Variable::Tuple(_) | Variable::Hash(_) | Variable::Enum(_) => {
+            // the copy has to exist before its items are copied in case one of them is the value itself
+            let copy: VarRef = Variable::None.into();
+            copies.insert(Rc::as_ptr(item), Rc::clone(&copy));
+            let value = match *item.borrow() {
+                Variable::List(ref vec) => Variable::List(vec.iter().map(|v| deep_copy_with(v, copies)).collect()),
+                Variable::Tuple(ref vec) => Variable::Tuple(vec.iter().map(|v| deep_copy_with(v, copies)).collect()),
+                Variable::Hash(ref hash) => Variable::Hash(hash.iter().map(|(k, v)| (k.clone(), deep_copy_with(v, copies))).collect()),
+                Variable::Enum(ref value) => Variable::Enum(EnumValue { fields: value.fields.iter().map(|v| deep_copy_with(v, copies)).collect(), ..value.clone() }),
+                _ => unreachable!(),
+            };
+            *copy.borrow_mut() = value;
+            gc::track(&copy);
+            return copy;
         },
-    }.into()
+    };
+    value.into()
 }
\ No newline at end of file
@@ -6,11 +6,15 @@ pub struct Token {
     pub position: (usize, u64, u64),
 }
 
+use crate::bigint::BigInt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     Semicolon,
     Identifier(String),
     Int(i64),
+    /// An integer literal too large for an `i64`
+    BigIntToken(BigInt),
     Float(f64),
     StringToken(String),
     CharToken(char),
@@ -18,6 +22,7 @@ pub enum TokenType {
     Addition,
     Subtraction,
     Multiplication,
+    Power,
     Division,
     And,
     Or,
@@ -35,6 +40,10 @@ pub enum TokenType {
     Modulus,
     LessThan,
     GreaterThan,
+    DotDot,
+    FatArrow,
+    DoubleColon,
+    Arrow,
 }
 
 pub struct Tokenizer {
@@ -53,7 +62,7 @@ impl Tokenizer {
         self.index += 1;
         if char == '\n' {self.position.1 += 1; self.position.2 = 1;}
         else {self.position.2 += 1;}
-        return char;
+        char
     }
     fn add_token(&mut self, token_type: TokenType) {
         self.tokens.push(Token { token_type, position: self.position });
@@ -64,14 +73,17 @@ impl Tokenizer {
             *escaped = true;
             match self.get_next() {
                 '\'' => next_char = '\'',
+                '"' => next_char = '"',
                 '\\' => next_char = '\\',
                 'n' => next_char = '\n',
                 'r' => next_char = '\r',
+                't' => next_char = '\t',
+                '0' => next_char = '\0',
                 _ => todo!()
             }
         }
 
-        return next_char;
+        next_char
     }
     pub fn generate(&mut self) -> Vec<Token> {
         loop {
@@ -102,6 +114,14 @@ impl Tokenizer {
                     continue;
                 }
 
+                // check for `=>`
+                if current_char == '=' && self.input[self.index+1] == '>' {
+                    self.add_token(TokenType::FatArrow);
+                    self.get_next();
+                    self.get_next();
+                    continue;
+                }
+
                 // check for `!=`
                 if current_char == '!' && self.input[self.index+1] == '=' {
                     self.add_token(TokenType::NotEqual);
@@ -109,6 +129,38 @@ impl Tokenizer {
                     self.get_next();
                     continue;
                 }
+
+                // check for `**`
+                if current_char == '*' && self.input[self.index+1] == '*' {
+                    self.add_token(TokenType::Power);
+                    self.get_next();
+                    self.get_next();
+                    continue;
+                }
+
+                // check for `->`
+                if current_char == '-' && self.input[self.index+1] == '>' {
+                    self.add_token(TokenType::Arrow);
+                    self.get_next();
+                    self.get_next();
+                    continue;
+                }
+
+                // check for `::`
+                if current_char == ':' && self.input[self.index+1] == ':' {
+                    self.add_token(TokenType::DoubleColon);
+                    self.get_next();
+                    self.get_next();
+                    continue;
+                }
+
+                // check for `..`
+                if current_char == '.' && self.input[self.index+1] == '.' {
+                    self.add_token(TokenType::DotDot);
+                    self.get_next();
+                    self.get_next();
+                    continue;
+                }
             }
 
             // strings
@@ -138,18 +190,17 @@ impl Tokenizer {
 
             // numbers
             if current_char.is_ascii_digit() {
-                let mut int = self.get_next().to_digit(10).unwrap() as i64;
+                let mut digits = String::from(self.get_next());
                 loop {
                     let next_char = self.input[self.index];
                     if !next_char.is_ascii_digit() {break;}
-                    int *= 10;
-                    int += next_char.to_digit(10).unwrap() as i64;
+                    digits.push(next_char);
                     self.get_next();
                 }
 
                 if self.input[self.index] == '.' {
                     self.get_next();
-                    let mut float = int as f64;
+                    let mut float: f64 = digits.parse().unwrap();
                     let mut decimal = 10.0;
                     loop {
                         let next_char = self.input[self.index];
@@ -162,7 +213,10 @@ impl Tokenizer {
                     continue;
                 }
 
-                self.add_token(TokenType::Int(int));
+                match digits.parse() {
+                    Ok(int) => self.add_token(TokenType::Int(int)),
+                    Err(_) => self.add_token(TokenType::BigIntToken(digits.parse().unwrap())),
+                }
                 continue;
             }
 
@@ -204,6 +258,6 @@ impl Tokenizer {
             }
             self.get_next();
         }
-        return self.tokens.clone();
+        self.tokens.clone()
     }
 }
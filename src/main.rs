@@ -1,10 +1,13 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, process};
 
-use ast::FunctionDecleration;
+use args::Command;
+
+use ast::{ASTree, EnumDecleration, FunctionDecleration};
 
 use lex::Tokenizer;
 
 mod args;
+mod bigint;
 mod files;
 mod lex;
 mod ast;
@@ -13,13 +16,17 @@ mod interpreter;
 fn main() -> Result<(), ()> {
     
     let mut all_functions: Vec<FunctionDecleration> = Vec::new();
+    let mut all_enums: Vec<EnumDecleration> = Vec::new();
+    // the top level `let` and `const` declarations of each file
+    let mut all_globals: Vec<Vec<ASTree>> = Vec::new();
 
     let orig_path;
     
     // hold a list of all files that need to be read from
     let mut files_to_read: Vec<String> = Vec::new();
-    match args::get_arg() {
-        Some(x) => {
+    let command = args::get_command();
+    match &command {
+        Some(Command::Run(x) | Command::Check { path: x, .. } | Command::Test(x)) => {
             orig_path = PathBuf::from(x);
             files_to_read.push(orig_path.file_name().unwrap().to_str().unwrap().to_owned());
         },
@@ -28,20 +35,50 @@ fn main() -> Result<(), ()> {
     
     let mut file_index = 0;
     while files_to_read.len() > file_index {
-        let file = files::read_full_file(&orig_path.parent().unwrap().join(&PathBuf::from(files_to_read[file_index].clone())))?;
+        let file = files::read_full_file(&orig_path.parent().unwrap().join(PathBuf::from(files_to_read[file_index].clone())))?;
         let tokens = Tokenizer::new(&file, file_index).generate();
 
-        let (mut paths, mut functions) = ast::ASTParser::new(tokens).parse_all();
+        let (mut paths, mut functions, mut enums, globals) = ast::ASTParser::new(tokens).parse_all();
         files_to_read.append(&mut paths);
         all_functions.append(&mut functions);
+        all_enums.append(&mut enums);
+        all_globals.push(globals);
         
         file_index += 1;
     }
 
+    // a misspelt type name is an error before anything runs
+    let errors = interpreter::check::check_annotations(&all_functions, &all_enums, &all_globals);
+    for error in &errors {
+        println!("{error}");
+    }
+    if !errors.is_empty() {process::exit(1);}
 
+    if let Some(Command::Check { types, .. }) = command {
+        if types {
+            let errors = interpreter::check::check_types(&all_functions, &all_enums, &all_globals);
+            for error in &errors {
+                println!("{error}");
+            }
+            if !errors.is_empty() {process::exit(1);}
+        }
+        return Ok(());
+    }
+
+    // each file can be referred to by its name without the extension
+    let modules = files_to_read.iter().map(|f| PathBuf::from(f).file_stem().unwrap().to_str().unwrap().to_owned()).collect();
+
+    if let Some(Command::Test(_)) = command {
+        let paths = files_to_read.iter().map(|f| orig_path.parent().unwrap().join(f).display().to_string()).collect();
+        let passed = interpreter::testing::run_tests(all_functions, all_enums, all_globals, modules, paths);
+        if !passed {process::exit(1);}
+        return Ok(());
+    }
     std::thread::Builder::new().stack_size(8 * 1024 * 1024).spawn(||{
-        let mut code_state = interpreter::CodeState::new(all_functions);
-        let result = code_state.run_function("main", &Vec::new(), (0,0,0));
+        let mut code_state = interpreter::CodeState::new(all_functions, all_enums, modules);
+        // imported files are set up before the files that import them
+        let globals: Vec<ASTree> = all_globals.into_iter().rev().flatten().collect();
+        let result = code_state.run_globals(&globals).and_then(|_| code_state.run_function("main", &Vec::new(), (0,0,0)));
         match result {
             Ok(_) => (),
             Err(x) => println!("{x}"),
@@ -0,0 +1,57 @@
+//! Type annotations on variables, constants, parameters and return values.
+
+mod common;
+
+use common::{run, run_with};
+
+#[test]
+fn matching_annotations_run() {
+    let output = run("annotations", r#"
+enum Shape { Circle(r), Square(s) }
+
+const LIMIT: Int = 3;
+
+func area(s: Shape) -> Float {
+    return match s { Shape::Circle(r) => 3.0 * r * r, Shape::Square(x) => x * x };
+}
+
+func main() {
+    let x: Int = 1;
+    x = 2;
+    let t: Tuple = (1, 2);
+    print(x, " ", t, " ", LIMIT, " ", area(Shape::Square(2.5)));
+}
+"#);
+    assert_eq!(output, "2 (1, 2) 3 6.25\n");
+}
+
+#[test]
+fn mismatched_annotations_are_errors() {
+    let output = run("mismatched_annotation", r#"
+func main() {
+    let name: String = 5;
+}
+"#);
+    assert_eq!(output, "At (0, 3, 25): Expected type String for variable `name`, got type Int\n");
+}
+
+#[test]
+fn unknown_type_names_are_errors_before_running() {
+    let source = r#"
+func main() {
+    print("never runs");
+}
+
+func unused(a: Itn) {
+    if (true) {
+        let x: Strng = "a";
+    }
+}
+"#;
+    let expected = "At (0, 6, 12): Unknown type: Itn\nAt (0, 8, 27): Unknown type: Strng\n";
+    for args in [&[][..], &["check"]] {
+        let output = run_with(args, "unknown_type", source);
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+}
@@ -0,0 +1,55 @@
+//! Ints that overflow an `i64` carry on as arbitrary precision integers and come back once they fit again.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn factorials_overflow_into_big_integers() {
+    let output = run("factorial", r#"
+func fact(n) {
+    if (n < 2) {return 1;}
+    return n * fact(n - 1);
+}
+func main() {
+    print(fact(20));
+    print(fact(30));
+    print(fact(60) / (fact(30) * fact(30)));
+    print(fact(30) / fact(28), " ", type(fact(30) / fact(28)));
+    print(fact(25) % 1000000007);
+}
+"#);
+    assert_eq!(output, "2432902008176640000\n265252859812191058636308480000000\n118264581564861424\n870 Int\n440732388\n");
+}
+
+#[test]
+fn overflow_at_the_edges() {
+    let output = run("edges", r#"
+func main() {
+    let max = 9223372036854775807;
+    let min = 0 - max - 1;
+    print(max + 1);
+    print(min - 1);
+    print(min / (0 - 1));
+    print(max * max);
+    print(max + 1 - 1 == max);
+}
+"#);
+    assert_eq!(output, "9223372036854775808\n-9223372036854775809\n9223372036854775808\n85070591730234615847396907784232501249\ntrue\n");
+}
+
+#[test]
+fn converting_big_integers() {
+    let output = run("convert", r#"
+func main() {
+    let big = 123456789012345678901234567890;
+    print(str(big) == "123456789012345678901234567890");
+    print(int("-99999999999999999999999") + 1);
+    print(float(big) > 1.0);
+    print(big > 5, " ", 0 - big < 5);
+    print(format("{:x}", big));
+    print(json_stringify([big]));
+}
+"#);
+    assert_eq!(output, "true\n-99999999999999999999998\ntrue\ntrue true\n18ee90ff6c373e0ee4e3f0ad2\n[123456789012345678901234567890]\n");
+}
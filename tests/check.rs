@@ -0,0 +1,121 @@
+//! `beetle check --types` reports the type errors it can find without running the program.
+
+mod common;
+
+use std::{fs, process::Command};
+
+use common::run_with;
+
+/// Checks a program and gives back its exit code and what it printed
+fn check_types(name: &str, source: &str) -> (Option<i32>, String) {
+    let output = run_with(&["check", "--types"], name, source);
+    (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn correct_programs_pass() {
+    let (code, output) = check_types("check_correct", r#"
+const GREETING: String = "hi";
+let count = 0;
+
+func twice(x: Int) -> Int {
+    return x * 2;
+}
+
+func main() {
+    let n = twice(3);
+    let things = [1, "a", 2.5];
+    for thing in things {
+        print(thing, " ", n + 1);
+    }
+    let x = 1;
+    x = "now a String";
+    print(x + "!", GREETING, count);
+}
+"#);
+    assert_eq!((code, output.as_str()), (Some(0), ""));
+}
+
+#[test]
+fn mismatches_in_functions() {
+    let (code, output) = check_types("check_functions", r#"
+func name() -> String {
+    return 1;
+}
+
+func main() {
+    let a = "a" - 1;
+    let b: Float = 2;
+    for c in "abc" {
+        print(c);
+    }
+}
+"#);
+    assert_eq!(code, Some(1));
+    assert_eq!(output, "\
+At (0, 3, 13): Expected type String for the return value of `name`, got type Int
+At (0, 7, 20): No operation found for Subtraction of String and Int
+At (0, 8, 21): Expected type Float for variable `b`, got type Int
+At (0, 11, 5): Expected type List, got type String
+");
+}
+
+#[test]
+fn mismatches_in_globals() {
+    let (code, output) = check_types("check_globals", r#"
+const NAME: String = 5;
+let word = "abc";
+let total: Int = word;
+let sum = word + 1;
+
+func main() {}
+"#);
+    assert_eq!(code, Some(1));
+    assert_eq!(output, "\
+At (0, 2, 23): Expected type String for constant `NAME`, got type Int
+At (0, 4, 22): Expected type Int for variable `total`, got type String
+At (0, 5, 19): No operation found for Addition of String and Int
+");
+}
+
+#[test]
+fn ref_arguments_count_as_reassigned() {
+    let source = r#"
+func to_text(ref value) {
+    value = "now a String";
+}
+
+func main() {
+    let x = 1;
+    to_text(x);
+    print(x + "!");
+    let h = {"a": 1};
+    h[h] = 1;
+}
+"#;
+    let (code, output) = check_types("check_ref", source);
+    assert_eq!((code, output.as_str()), (Some(0), ""));
+    // reading the index before changing the Hash makes using it as its own key an error, not a crash
+    let output = common::run("ref_run", source);
+    assert_eq!(output, "now a String!\nAt (0, 11, 13): A value of type Hash cannot be a Hash key or in a Set\n");
+}
+
+#[test]
+fn globals_of_other_files() {
+    let directory = std::env::temp_dir().join(format!("beetle_check_modules_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("lib.bt"), "const GREETING = \"hi\";\n").unwrap();
+    fs::write(directory.join("main.bt"), r#"import "lib.bt"
+
+enum Colour { Red, Green }
+
+func main() {
+    print(lib::GREETING + "!");
+    let colour = Colour::Red + 1;
+}
+"#).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_beetle")).args(["check", "--types"]).arg(directory.join("main.bt")).output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    // only the enum is known to be the wrong type
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "At (0, 7, 33): No operation found for Addition of Colour and Int\n");
+}
@@ -0,0 +1,23 @@
+//! Runs Beetle programs for the integration tests.
+
+// each test file only uses some of these
+#![allow(dead_code)]
+
+use std::{fs, path::PathBuf, process::{Command, Output}};
+
+/// Writes a program to a temporary file and runs `beetle` with `args` followed by its path
+pub fn run_with(args: &[&str], name: &str, source: &str) -> Output {
+    let path: PathBuf = std::env::temp_dir().join(format!("beetle_{name}_{}.bt", std::process::id()));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_beetle")).args(args).arg(&path).output().unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+/// Runs a program and gives back what it printed. Errors in the program are printed, but the interpreter
+/// crashing fails the test
+pub fn run(name: &str, source: &str) -> String {
+    let output = run_with(&[], name, source);
+    assert!(output.status.success(), "`{name}` exited with {}\n{}", output.status, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
@@ -0,0 +1,58 @@
+//! Values that contain themselves, directly or through other values, can be copied, printed and freed.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn printing_a_cycle() {
+    let output = run("print", r#"
+func main() {
+    let xs = [1];
+    push(xs, xs);
+    let h = {"a": 1};
+    h["self"] = h;
+    print(xs, " ", h);
+}
+"#);
+    assert_eq!(output, "[1, [...]] {\"a\": 1, \"self\": {...}}\n");
+}
+
+#[test]
+fn copying_a_cycle() {
+    let output = run("copy", r#"
+func main() {
+    let xs = [1];
+    push(xs, xs);
+    let ys = copy(xs);
+    ys[0] = 2;
+    print(xs[0], " ", ys[0], " ", ys[1][0]);
+}
+"#);
+    assert_eq!(output, "1 2 2\n");
+}
+
+#[test]
+fn cycles_are_collected() {
+    let output = run("collect", r#"
+func make() {
+    let a = [1];
+    push(a, a);
+    let h = {};
+    let g = {"h": h};
+    h["g"] = g;
+}
+func main() {
+    let kept = [0];
+    push(kept, kept);
+    for i in range(50) {
+        make();
+    }
+    print(gc());
+    print(gc());
+    print(gc_stats()["collected"]);
+    print(len(kept));
+}
+"#);
+    assert_eq!(output, "150\n0\n150\n2\n");
+}
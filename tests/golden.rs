@@ -0,0 +1,93 @@
+//! Runs every program in `example` and `tests/golden` and compares what it does with the `.out` file next to it.
+//!
+//! A program gets the `.stdin` file next to it as its input, or no input when there is none. The `.out` file
+//! holds what it printed, followed by what it printed to stderr and its exit code when those are not empty and 0.
+//!
+//! To update the `.out` files after a change to what a program prints, run `BLESS=1 cargo test --test golden`
+//! and check the differences before committing them.
+
+use std::{fs, io::Write, path::{Path, PathBuf}, process::{Command, Stdio}};
+
+const DIRECTORIES: [&str; 2] = ["example", "tests/golden"];
+
+/// Programs whose output depends on more than their input
+const SKIPPED: [(&str, &str); 9] = [
+    ("http_client.bt", "needs a server"),
+    ("http_server.bt", "runs until stopped"),
+    ("tcp.bt", "runs until stopped"),
+    ("tcp_client.bt", "needs a server"),
+    ("udp.bt", "binds a port"),
+    ("tictactoe.bt", "the computer plays random moves"),
+    ("lib.bt", "only imported by other programs"),
+    ("globals_lib.bt", "only imported by globals.bt"),
+    ("tests.bt", "run with `beetle test` in tests/testing.rs"),
+];
+
+/// Runs a program from the root of the repo, so paths in the output are the same on every machine
+fn run(program: &Path) -> String {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let stdin = fs::read(program.with_extension("stdin")).unwrap_or_default();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_beetle"))
+        .arg(program.strip_prefix(root).unwrap())
+        .current_dir(root)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn().unwrap();
+    // a program that stops reading early closes its input, which is not an error here
+    let _ = child.stdin.take().unwrap().write_all(&stdin);
+    let output = child.wait_with_output().unwrap();
+
+    let mut result = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+    if !output.stderr.is_empty() {
+        result += &format!("--- stderr\n{}", String::from_utf8_lossy(&output.stderr).replace("\r\n", "\n"));
+    }
+    match output.status.code() {
+        Some(0) => {}
+        Some(code) => result += &format!("--- exit code {code}\n"),
+        None => result += "--- killed by a signal\n",
+    }
+    result
+}
+
+/// The line number and both lines where the outputs first differ
+fn first_difference(expected: &str, actual: &str) -> String {
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => {}
+            (e, a) => return format!("line {line}\n  expected: {}\n    actual: {}", e.unwrap_or("<end of output>"), a.unwrap_or("<end of output>")),
+        }
+    }
+    "only the line endings differ".to_owned()
+}
+
+#[test]
+fn programs_match_their_output() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut programs: Vec<PathBuf> = DIRECTORIES.iter()
+        .flat_map(|directory| fs::read_dir(root.join(directory)).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "bt"))
+        .filter(|path| !SKIPPED.iter().any(|(name, _)| path.file_name().unwrap() == *name))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty());
+
+    let mut failures = Vec::new();
+    for program in &programs {
+        let actual = run(program);
+        let name = program.strip_prefix(root).unwrap().display();
+        let out = program.with_extension("out");
+        if bless {
+            fs::write(&out, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&out) {
+            Ok(expected) if expected.replace("\r\n", "\n") == actual => {}
+            Ok(expected) => failures.push(format!("{name}: differs at {}", first_difference(&expected, &actual))),
+            Err(_) => failures.push(format!("{name}: there is no .out file")),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}\n\nrun with BLESS=1 to update the .out files", failures.join("\n\n"));
+}
@@ -0,0 +1,104 @@
+//! An `http_serve` server and the `http_get` and `http_post` clients talking over the loopback interface.
+
+mod common;
+
+use std::{fs, io::{BufRead, BufReader, Read, Write}, net::TcpStream, process::{Child, Command, Stdio}, time::{Duration, Instant}};
+
+use common::run;
+
+const SERVER: &str = r#"
+func main() {
+    let server = tcp_bind("127.0.0.1:0");
+    print(local_addr(server));
+    http_serve(server, {
+        "GET /hello": "hello",
+        "POST /echo": "echo",
+        "GET /broken": "broken",
+    });
+}
+
+func hello(request) {
+    return "Hello, " + request["query"]["name"] + "!";
+}
+
+func echo(request) {
+    return {"status": 201, "headers": {"X-Method": request["method"]}, "body": request["body"]};
+}
+
+func broken(request) {
+    return 5;
+}
+"#;
+
+/// Kills the server when the test ends, even when it fails
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts the server and gives back the address it is listening on
+fn start_server() -> (Server, String) {
+    let path = std::env::temp_dir().join(format!("beetle_http_server_{}.bt", std::process::id()));
+    fs::write(&path, SERVER).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_beetle")).arg(&path)
+        .stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    let mut address = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut address).unwrap();
+    fs::remove_file(&path).unwrap();
+    (Server(child), address.trim().to_owned())
+}
+
+/// Sends raw bytes to the server and gives back the status line of the response
+fn send_raw(address: &str, request: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response.lines().next().unwrap_or_default().to_owned()
+}
+
+#[test]
+fn round_trip() {
+    let (_server, address) = start_server();
+
+    assert_eq!(send_raw(&address, "nonsense\r\n\r\n"), "HTTP/1.1 400 Bad Request");
+    assert_eq!(send_raw(&address, "GET / HTTP/1.1\r\nno colon here\r\n\r\n"), "HTTP/1.1 400 Bad Request");
+    // the client leaving without sending anything does not stop the server either
+    drop(TcpStream::connect(&address).unwrap());
+
+    let output = run("http_client", &format!(r#"
+func main() {{
+    let response = http_get("http://{address}/hello?name=Beetle%20fan");
+    print(response["status"], " ", response["body"]);
+    let response = http_post("http://{address}/echo", "ping");
+    print(response["status"], " ", response["headers"]["x-method"], " ", response["body"]);
+    let response = http_get("http://{address}/missing");
+    print(response["status"], " ", response["body"]);
+    let response = http_get("http://{address}/broken");
+    print(response["status"], " ", response["body"]);
+    print(http_get("http://{address}/hello?name=again")["body"]);
+}}
+"#));
+    assert_eq!(output, "200 Hello, Beetle fan!\n201 POST ping\n404 Not Found\n500 Internal Server Error\nHello, again!\n");
+}
+
+#[test]
+fn silent_clients_time_out() {
+    let (_server, address) = start_server();
+
+    // a client that never sends its request gets a 408 once the server stops waiting
+    let start = Instant::now();
+    let mut silent = TcpStream::connect(&address).unwrap();
+    silent.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let mut response = String::new();
+    silent.read_to_string(&mut response).unwrap();
+    assert_eq!(response.lines().next(), Some("HTTP/1.1 408 Request Timeout"));
+    assert!(start.elapsed() < Duration::from_secs(30));
+
+    // and the server goes on to answer the next one
+    assert_eq!(send_raw(&address, "GET /hello?name=x HTTP/1.1\r\n\r\n"), "HTTP/1.1 200 OK");
+}
@@ -0,0 +1,54 @@
+//! `json_parse` and `json_stringify`, and where `json_parse` reports malformed input.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn floats_stay_floats() {
+    let output = run("json_floats", r#"
+func main() {
+    for x in [100000000000000000000.0, 1.5, 0.1, 3.0, 0.000001] {
+        let text = json_stringify(x);
+        let back = json_parse(text);
+        print(text, " ", type(back), " ", back == x);
+    }
+}
+"#);
+    assert_eq!(output, "100000000000000000000.0 Float true\n1.5 Float true\n0.1 Float true\n3.0 Float true\n0.000001 Float true\n");
+}
+
+#[test]
+fn error_positions() {
+    let cases = [
+        (r#"{\"a\": 1,\n  \"b\": tru}"#, "line 2, column 11: invalid literal, expected `true`"),
+        ("[1, 2", "line 1, column 6: expected `,` or `]` in array"),
+        (r#"{\"a\" 1}"#, "line 1, column 6: expected `:`, found `1`"),
+        (r#"\"abc"#, "line 1, column 5: unterminated string"),
+        ("[1] x", "line 1, column 5: unexpected `x` after value"),
+        (r#"\"\\uD800\""#, "line 1, column 9: unpaired surrogate in unicode escape"),
+        ("01", "line 1, column 2: unexpected `1` after value"),
+        ("", "line 1, column 1: unexpected end of input"),
+    ];
+    for (i, (json, message)) in cases.iter().enumerate() {
+        let output = run(&format!("json_error_{i}"), &format!("func main() {{\n    print(json_parse(\"{json}\"));\n}}\n"));
+        assert!(output.ends_with(&format!("Invalid JSON at {message}\n")), "{json}: {output}");
+    }
+}
+
+#[test]
+fn nesting_is_limited() {
+    let output = run("json_nesting", r#"
+func main() {
+    let open = "";
+    let close = "";
+    for i in range(256) {
+        open = open + "[";
+        close = close + "]";
+    }
+    print(len(json_parse(open + close)));
+    json_parse(open + "[[[[" + close);
+}
+"#);
+    assert_eq!(output, "1\nAt (0, 10, 38): Invalid JSON at line 1, column 257: nested more than 256 deep\n");
+}
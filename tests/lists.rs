@@ -0,0 +1,48 @@
+//! Checks the list built-ins report bad indices and arguments instead of panicking.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn list_built_ins() {
+    let output = run("list_built_ins", r#"
+func main() {
+    let xs = [1, 2, 3];
+    insert(xs, 3, 4);
+    insert(xs, 0, 0);
+    print(remove(xs, 1), " ", xs);
+    let s = "abc";
+    set(s, 0, 'x');
+    print(s, " ", split("a,b", ","), " ", range(2));
+}
+"#);
+    assert_eq!(output, "1 [0, 2, 3, 4]\nxbc [\"a\", \"b\"] [0, 1]\n");
+}
+
+#[test]
+fn indices_out_of_range() {
+    for (name, call, expected) in [
+        ("insert_past_end", "insert(xs, 4, 0)", "Index 4 is out of range for length 3"),
+        ("insert_negative", "insert(xs, 0 - 1, 0)", "Index -1 is out of range for length 3"),
+        ("remove_past_end", "remove(xs, 3)", "Index 3 is out of range for length 3"),
+        ("set_past_end", "set(xs, 3, 0)", "Index 3 is out of range for length 3"),
+    ] {
+        let output = run(name, &format!("func main() {{\n    let xs = [1, 2, 3];\n    let r = {call};\n}}\n"));
+        assert!(output.contains(expected), "{call}: {output}");
+    }
+}
+
+#[test]
+fn arguments_of_the_wrong_type() {
+    for (name, call, expected) in [
+        ("insert_list_index", "insert(xs, xs, 0)", "Expected type Int, got type List"),
+        ("remove_from_string", "remove(\"ab\", 0)", "Expected type List, got type String"),
+        ("split_by_int", "split(\"a,b\", 1)", "Expected type String, got type Int"),
+        ("range_of_string", "range(\"3\")", "Expected type Int, got type String"),
+        ("gc_with_argument", "gc(1)", "Incorrect arguments passed to function"),
+    ] {
+        let output = run(name, &format!("func main() {{\n    let xs = [1, 2, 3];\n    let r = {call};\n}}\n"));
+        assert!(output.contains(expected), "{call}: {output}");
+    }
+}
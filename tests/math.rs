@@ -0,0 +1,68 @@
+//! The `**` operator and the math built-ins, across Ints, Floats and big integers.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn power_operator() {
+    let output = run("power", r#"
+func main() {
+    print(2 ** 10, " ", 2 ** 3 ** 2, " ", 0 - 2 ** 2, " ", 2 * 3 ** 2);
+    print(2 ** 100);
+    print(2 ** (0 - 1), " ", 4 ** 0.5, " ", pow(3, 4));
+}
+"#);
+    assert_eq!(output, "1024 512 -4 18\n1267650600228229401496703205376\n0.5 2 81\n");
+}
+
+#[test]
+fn rounding_gives_ints() {
+    let output = run("rounding", r#"
+func main() {
+    print(floor(2.7), " ", ceil(2.1), " ", round(2.5), " ", floor(0.0 - 2.5), " ", type(round(2.5)));
+    print(round(3.14159, 2), " ", floor(7));
+}
+"#);
+    assert_eq!(output, "2 3 3 -3 Int\n3.14 7\n");
+}
+
+#[test]
+fn comparing_numbers() {
+    let output = run("comparing", r#"
+func main() {
+    print(abs(0 - 5), " ", abs(0.0 - 2.5), " ", abs(0 - 2 ** 70));
+    print(min(3, 1, 2), " ", max([1, 5.5, 2]), " ", min("b", "a"));
+    print(clamp(15, 0, 10), " ", clamp(0 - 1, 0, 10), " ", clamp(5.5, 0, 10));
+    print(gcd(12, 18), " ", gcd(0 - 12, 18), " ", gcd(2 ** 80, 6 ** 40));
+}
+"#);
+    assert_eq!(output, "5 2.5 1180591620717411303424\n1 5.5 a\n10 0 5.5\n6 6 1099511627776\n");
+}
+
+#[test]
+fn float_functions() {
+    let output = run("floats", r#"
+func main() {
+    print(sqrt(16), " ", exp(0), " ", log(e()), " ", log(8, 2));
+    print(sin(0), " ", cos(0), " ", atan2(1, 1) * 4 == pi());
+    print(is_nan(sqrt(0 - 1)), " ", is_nan(1));
+}
+"#);
+    assert_eq!(output, "4 1 1 3\n0 1 true\ntrue false\n");
+}
+
+#[test]
+fn dividing_by_zero() {
+    let output = run("dividing", r#"
+func main() {
+    let min = 0 - 9223372036854775807 - 1;
+    print(min / (0 - 1), " ", min % (0 - 1), " ", 7 / 2, " ", 0 - 7 % 3, " ", 1.0 / 0, " ", 2 ** 70 % 1000);
+}
+"#);
+    assert_eq!(output, "9223372036854775808 0 3 -1 inf 424\n");
+    for (name, division) in [("int_zero", "1 / 0"), ("int_modulus", "1 % 0"), ("big_zero", "2 ** 70 / 0"), ("big_modulus", "2 ** 70 % 0")] {
+        let output = run(name, &format!("func main() {{\n    let x = {division};\n}}\n"));
+        assert!(output.ends_with(": Cannot divide an Int by zero\n"), "{name}: {output}");
+    }
+}
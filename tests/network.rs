@@ -0,0 +1,52 @@
+//! TCP and UDP sockets over the loopback interface.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn tcp_round_trip_and_closed_handles() {
+    let output = run("network_tcp", r#"
+func main() {
+    let listener = tcp_bind("127.0.0.1:0");
+    let client = tcp_connect(local_addr(listener));
+    let server = tcp_accept(listener);
+    print(peer_addr(server) == local_addr(client));
+
+    write(client, "hello\nworld\n");
+    print(read_line(server), " ", read_line(server));
+    close(client);
+    print(read_line(server));
+
+    // ids are not reused, so the closed handle does not reach the next socket
+    let again = tcp_connect(local_addr(listener));
+    print(client, " ", again);
+    close(again);
+    close(server);
+    write(client, "lost");
+}
+"#);
+    assert_eq!(output, "true\nhello world\nNone\n<socket 1> <socket 3>\nAt (0, 18, 26): Socket 1 is not open\n");
+}
+
+#[test]
+fn udp_round_trip() {
+    let output = run("network_udp", r#"
+func main() {
+    let server = udp_bind("127.0.0.1:0");
+    let client = udp_bind("127.0.0.1:0");
+    print(local_addr(server) != local_addr(client));
+
+    udp_send_to(client, "ping", local_addr(server));
+    let (message, sender) = udp_recv_from(server);
+    print(message, " ", sender == local_addr(client));
+    udp_send_to(server, "pong", sender);
+    let (message, sender) = udp_recv_from(client);
+    print(message, " ", sender == local_addr(server));
+
+    set_timeout(client, 20);
+    print(udp_recv_from(client));
+}
+"#);
+    assert_eq!(output, "true\nping true\npong true\nNone\n");
+}
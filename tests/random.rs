@@ -0,0 +1,58 @@
+//! The random built-ins give the same numbers every time once seeded.
+
+mod common;
+
+use common::run;
+
+const ROLLS: &str = r#"
+func main() {
+    seed(7);
+    let rolls = [];
+    for i in range(10) {push(rolls, random_int(1, 6));}
+    let deck = range(8);
+    shuffle(deck);
+    print(rolls, " ", deck, " ", choice(["a", "b", "c"]), " ", random());
+}
+"#;
+
+#[test]
+fn seeding_repeats_the_same_numbers() {
+    let first = run("seeded_first", ROLLS);
+    let second = run("seeded_second", ROLLS);
+    assert!(!first.is_empty());
+    assert_eq!(first, second);
+}
+
+#[test]
+fn numbers_stay_in_range() {
+    let output = run("range", r#"
+func main() {
+    seed(1);
+    let seen = set();
+    for i in range(1000) {
+        let roll = random_int(1, 6);
+        if (roll < 1 | roll > 6) {print("out of range ", roll);}
+        add(seen, roll);
+        let f = random();
+        if (f < 0.0 | f > 1.0 | f == 1.0) {print("out of range ", f);}
+    }
+    print(len(seen), " ", random_int(4, 4), " ", choice([]));
+    let deck = range(10);
+    shuffle(deck);
+    let sorted = [];
+    for i in range(10) {push(sorted, contains(deck, i));}
+    print(sorted);
+}
+"#);
+    assert_eq!(output, "6 4 None\n[true, true, true, true, true, true, true, true, true, true]\n");
+}
+
+#[test]
+fn empty_ranges_are_errors() {
+    let output = run("empty_range", r#"
+func main() {
+    random_int(3, 1);
+}
+"#);
+    assert!(output.contains("no numbers from 3 to 1"), "{output}");
+}
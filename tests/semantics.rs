@@ -0,0 +1,203 @@
+//! Locks down when values are shared and when they are copied.
+//!
+//! Ints, Floats, Bools, Chars, Strings, Tuples and enums are copied whenever they are bound to a name,
+//! passed to a function or put in a collection. Lists, Hashes and Sets are shared. Assigning to a name
+//! never changes any other name, except through a `ref` parameter.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn rebinding_a_copy_leaves_the_original() {
+    let output = run("rebind_int", r#"
+func main() {
+    let a = 1;
+    let b = a;
+    b = 5;
+    print(a, " ", b);
+}
+"#);
+    assert_eq!(output, "1 5\n");
+}
+
+#[test]
+fn lists_are_shared() {
+    let output = run("shared_list", r#"
+func main() {
+    let xs = [1, 2];
+    let ys = xs;
+    push(ys, 3);
+    ys[0] = 9;
+    print(xs);
+}
+"#);
+    assert_eq!(output, "[9, 2, 3]\n");
+}
+
+#[test]
+fn rebinding_a_shared_list_leaves_the_original() {
+    let output = run("rebind_list", r#"
+func main() {
+    let xs = [1, 2];
+    let ys = xs;
+    ys = [7];
+    print(xs, " ", ys);
+}
+"#);
+    assert_eq!(output, "[1, 2] [7]\n");
+}
+
+#[test]
+fn hashes_and_sets_are_shared() {
+    let output = run("shared_hash", r#"
+func main() {
+    let h = {"a": 1};
+    let g = h;
+    g["b"] = 2;
+    let s = {1};
+    let t = s;
+    add(t, 2);
+    print(h, " ", s);
+}
+"#);
+    assert_eq!(output, "{\"a\": 1, \"b\": 2} {1, 2}\n");
+}
+
+#[test]
+fn strings_are_copied() {
+    let output = run("copied_string", r#"
+func main() {
+    let s = "abc";
+    let t = s;
+    t[0] = 'x';
+    set(t, 1, 'y');
+    print(s, " ", t);
+}
+"#);
+    assert_eq!(output, "abc xyc\n");
+}
+
+#[test]
+fn arguments_are_passed_the_same_way() {
+    let output = run("arguments", r#"
+func change(n, xs) {
+    n = n + 1;
+    xs[0] = 99;
+    xs = [0];
+}
+func main() {
+    let n = 1;
+    let xs = [1, 2];
+    change(n, xs);
+    print(n, " ", xs);
+}
+"#);
+    assert_eq!(output, "1 [99, 2]\n");
+}
+
+#[test]
+fn ref_parameters_change_the_callers_variable() {
+    let output = run("ref_parameters", r#"
+func swap(ref player) {
+    if (player == "X") {player = "O";} else {player = "X";}
+}
+func reset(ref xs) {
+    xs = [];
+}
+func main() {
+    let player = "X";
+    swap(player);
+    print(player);
+    swap(player);
+    print(player);
+    let xs = [1];
+    let ys = xs;
+    reset(xs);
+    print(xs, " ", ys);
+}
+"#);
+    assert_eq!(output, "O\nX\n[] [1]\n");
+}
+
+#[test]
+fn ref_parameters_need_a_variable() {
+    let output = run("ref_needs_variable", r#"
+func bump(ref n) {
+    n = n + 1;
+}
+func main() {
+    bump(5);
+}
+"#);
+    assert!(output.contains("ref parameter n needs a variable"), "{output}");
+
+    let output = run("ref_needs_non_constant", r#"
+const LIMIT = 1;
+func bump(ref n) {
+    n = n + 1;
+}
+func main() {
+    bump(LIMIT);
+}
+"#);
+    assert!(output.contains("ref parameter n needs a variable"), "{output}");
+}
+
+#[test]
+fn assigning_an_item_replaces_it() {
+    let output = run("replace_item", r#"
+func main() {
+    let inner = [1];
+    let outer = [inner, inner];
+    outer[0] = 5;
+    push(inner, 2);
+    print(inner, " ", outer);
+}
+"#);
+    assert_eq!(output, "[1, 2] [5, [1, 2]]\n");
+}
+
+#[test]
+fn items_put_in_a_collection_are_copied_or_shared() {
+    let output = run("collection_items", r#"
+func main() {
+    let n = 1;
+    let xs = [0];
+    let items = [n, xs];
+    let pair = (n, xs);
+    n = 2;
+    push(xs, 1);
+    print(items, " ", pair);
+}
+"#);
+    assert_eq!(output, "[1, [0, 1]] (1, [0, 1])\n");
+}
+
+#[test]
+fn loop_variables_are_bound_like_let() {
+    let output = run("loop_variables", r#"
+func main() {
+    let xs = [1, 2];
+    let nested = [[1], [2]];
+    for x in xs {x = 0;}
+    for inner in nested {push(inner, 0);}
+    print(xs, " ", nested);
+}
+"#);
+    assert_eq!(output, "[1, 2] [[1, 0], [2, 0]]\n");
+}
+
+#[test]
+fn blocks_assign_to_the_outer_variable() {
+    let output = run("outer_variable", r#"
+func main() {
+    let i = 0;
+    while (i < 3) {
+        if (true) {i = i + 1;}
+    }
+    print(i);
+}
+"#);
+    assert_eq!(output, "3\n");
+}
@@ -0,0 +1,78 @@
+//! `beetle test` runs each test in a fresh interpreter and reports where the failures were.
+
+mod common;
+
+use std::process::Output;
+
+use common::{run, run_with};
+
+/// Runs `beetle test` on a program
+fn run_tests(name: &str, source: &str) -> Output {
+    run_with(&["test"], &format!("testing_{name}"), source)
+}
+
+#[test]
+fn passing_tests() {
+    let output = run_tests("passing", r#"
+let count = 0;
+
+test func counts() {
+    count = count + 1;
+    assert_eq(count, 1);
+}
+
+test func counts_again() {
+    count = count + 1;
+    assert_eq(count, 1);
+}
+
+func test_prefixed() {
+    assert_eq([1, {"a": (2, 3)}], [1, {"a": (2, 3)}]);
+    assert_eq(1, 1.0);
+    assert(true, "never shown");
+}
+
+func helper() {
+    assert(false);
+}
+
+func test_board(board) {
+    assert(false);
+}
+"#);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(stdout, "running 3 tests\ntest counts ... ok\ntest counts_again ... ok\ntest test_prefixed ... ok\n\ntest result: ok. 3 passed; 0 failed\n");
+}
+
+#[test]
+fn failing_tests() {
+    let output = run_tests("failing", r#"
+test func passes() {
+    assert(true);
+}
+test func not_equal() {
+    assert_eq([1, 2], [1, 3]);
+}
+test func with_message() {
+    assert(1 > 2, "one is not bigger");
+}
+test func errors() {
+    let y = missing;
+}
+"#);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(stdout.contains("test passes ... ok\ntest not_equal ... FAILED\ntest with_message ... FAILED\ntest errors ... FAILED\n"), "{stdout}");
+    assert!(stdout.contains(".bt:6:"), "{stdout}");
+    assert!(stdout.contains("Assertion failed, the values are not equal\n  left: [1, 2]\n right: [1, 3]\n"), "{stdout}");
+    assert!(stdout.contains("Assertion failed: one is not bigger\n"), "{stdout}");
+    assert!(stdout.contains("Cannot find variable: missing\n"), "{stdout}");
+    assert!(stdout.ends_with("test result: FAILED. 1 passed; 3 failed\n"), "{stdout}");
+}
+
+#[test]
+fn asserts_stop_a_normal_run() {
+    let output = run("testing_normal_run", "func main() {\n    assert_eq(1 + 1, 3);\n    print(\"not reached\");\n}\n");
+    assert_eq!(output, "At (0, 2, 24): Assertion failed, the values are not equal\n  left: 2\n right: 3\n");
+}
@@ -0,0 +1,101 @@
+//! Clocks, sleeping and converting UTC timestamps to and from dates.
+
+mod common;
+
+use common::run;
+
+#[test]
+fn clocks_move_forward() {
+    let output = run("clocks", r#"
+func main() {
+    print(now() > 1700000000.0);
+    let start = monotonic();
+    sleep(20);
+    print(monotonic() - start > 0.019);
+}
+"#);
+    assert_eq!(output, "true\ntrue\n");
+}
+
+#[test]
+fn sleeping_too_long_is_an_error() {
+    let output = run("sleep_forever", "func main() {\n    sleep(10.0 ** 400.0);\n}\n");
+    assert_eq!(output, "At (0, 2, 25): Cannot sleep for inf milliseconds\n");
+    let output = run("sleep_too_long", "func main() {\n    sleep(10.0 ** 30.0);\n}\n");
+    assert_eq!(output, "At (0, 2, 24): Cannot sleep for 1000000000000000000000000000000 milliseconds\n");
+}
+
+#[test]
+fn dates_of_timestamps() {
+    let output = run("dates", r#"
+func main() {
+    print(date(951782400));
+    let before = date(0 - 1);
+    print(before["year"], " ", before["month"], " ", before["day"], " ", before["second"]);
+    print(format_date(1709993100.25));
+    print(format_date(1709993100.25, "%a %d %b %Y %H:%M:%S.%f, %A %B, day %j, 100%%"));
+}
+"#);
+    assert_eq!(output, concat!(
+        "{\"year\": 2000, \"month\": 2, \"day\": 29, \"hour\": 0, \"minute\": 0, \"second\": 0, \"millisecond\": 0, \"weekday\": 2, \"yearday\": 60}\n",
+        "1969 12 31 59\n",
+        "2024-03-09T14:05:00Z\n",
+        "Sat 09 Mar 2024 14:05:00.250, Saturday March, day 069, 100%\n",
+    ));
+}
+
+#[test]
+fn timestamps_of_dates() {
+    let output = run("timestamps", r#"
+func main() {
+    print(parse_date("2024-03-09T14:05:00Z"));
+    print(parse_date("9 March 2024", "%d %B %Y"), " ", parse_date("9 Mar 2024", "%d %b %Y"));
+    print(parse_date("2023-02-29", "%Y-%m-%d"), " ", parse_date("yesterday"));
+    print(timestamp({"year": 2024, "month": 3, "day": 9, "hour": 14, "minute": 5}));
+    print(timestamp(date(1234567890)));
+}
+"#);
+    assert_eq!(output, "1709993100\n1709942400 1709942400\nNone None\n1709993100\n1234567890\n");
+}
+
+#[test]
+fn invalid_dates_are_errors() {
+    let output = run("invalid_date", r#"
+func main() {
+    timestamp({"year": 2023, "month": 2, "day": 29});
+}
+"#);
+    assert!(output.contains("day 29 is out of range for February 2023"), "{output}");
+
+    let output = run("invalid_directive", r#"
+func main() {
+    format_date(0, "%Q");
+}
+"#);
+    assert!(output.contains("unknown date directive `%Q`"), "{output}");
+}
+
+#[test]
+fn dates_out_of_range_are_errors() {
+    let output = run("huge_year", "func main() {\n    timestamp({\"year\": 100000000000000000});\n}\n");
+    assert_eq!(output, "At (0, 2, 44): Invalid date: year 100000000000000000 is out of range\n");
+    let output = run("huge_timestamp", "func main() {\n    date(0 - 10.0 ** 300.0);\n}\n");
+    assert_eq!(output, "At (0, 2, 28): Invalid date: timestamp -1e300 is out of range\n");
+
+    // the furthest dates that are allowed still work both ways
+    let output = run("furthest_date", r#"
+func main() {
+    let last = timestamp({"year": 10000000, "month": 12, "day": 31, "hour": 23});
+    print(last, " ", format_date(last), " ", parse_date("10000001", "%Y"));
+}
+"#);
+    assert_eq!(output, "315507384399600 10000000-12-31T23:00:00Z None\n");
+}
+
+#[test]
+fn timestamps_that_are_not_numbers_are_errors() {
+    let output = run("nan_date", "func main() {\n    date(0.0 / 0.0);\n}\n");
+    assert_eq!(output, "At (0, 2, 20): Invalid date: timestamp NaN is not a number of seconds\n");
+    let output = run("inf_date", "func main() {\n    format_date(10.0 ** 400.0);\n}\n");
+    assert_eq!(output, "At (0, 2, 31): Invalid date: timestamp inf is not a number of seconds\n");
+}